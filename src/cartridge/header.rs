@@ -0,0 +1,442 @@
+use std::error;
+use std::fmt;
+
+use byteorder::{BigEndian, ByteOrder};
+
+use super::ROM_BANK_SIZE;
+
+const HEADER_END: usize = 0x0150;
+
+const LOGO_START: usize = 0x0104;
+const LOGO_END: usize = 0x0134;
+
+const TITLE_START: usize = 0x0134;
+const TITLE_END: usize = 0x0144;
+const CGB_TITLE_END: usize = 0x013F;
+
+const MANUFACTURER_CODE_START: usize = 0x013F;
+const MANUFACTURER_CODE_END: usize = 0x0143;
+
+const CGB_FLAG: usize = 0x0143;
+const NEW_LICENSEE_CODE_START: usize = 0x0144;
+const NEW_LICENSEE_CODE_END: usize = 0x0146;
+const SGB_FLAG: usize = 0x0146;
+const CARTRIDGE_TYPE: usize = 0x0147;
+const ROM_SIZE: usize = 0x0148;
+const RAM_SIZE: usize = 0x0149;
+const DESTINATION_CODE: usize = 0x014A;
+const OLD_LICENSEE_CODE: usize = 0x014B;
+const VERSION: usize = 0x014C;
+const HEADER_CHECKSUM: usize = 0x014D;
+const GLOBAL_CHECKSUM: usize = 0x014E;
+
+const HEADER_CHECKSUM_START: usize = 0x0134;
+
+// Old licensee code signalling that the new licensee code is used instead
+const USE_NEW_LICENSEE_CODE: u8 = 0x33;
+
+pub const NINTENDO_LOGO: [u8; LOGO_END - LOGO_START] = [
+  0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+  0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+  0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+#[derive(Debug, PartialEq)]
+pub enum CartridgeError {
+  Truncated(usize),
+  InvalidCartridgeType(u8),
+  InvalidRomSize(u8),
+  InvalidRamSize(u8),
+  RomSizeMismatch { expected: usize, actual: usize },
+  InvalidLogo,
+  HeaderChecksumMismatch { expected: u8, actual: u8 },
+  GlobalChecksumMismatch { expected: u16, actual: u16 },
+  UnsupportedMapper(Mapper),
+}
+
+impl fmt::Display for CartridgeError {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match *self {
+      CartridgeError::Truncated(size) => {
+        write!(f, "ROM is {} bytes, too short to contain a cartridge header", size)
+      }
+      CartridgeError::InvalidCartridgeType(code) => {
+        write!(f, "unknown cartridge type {:#04x}", code)
+      }
+      CartridgeError::InvalidRomSize(code) => write!(f, "unknown ROM size code {:#04x}", code),
+      CartridgeError::InvalidRamSize(code) => write!(f, "unknown RAM size code {:#04x}", code),
+      CartridgeError::RomSizeMismatch { expected, actual } => {
+        write!(f, "header declares {} bytes of ROM but {} were supplied", expected, actual)
+      }
+      CartridgeError::InvalidLogo => write!(f, "Nintendo logo does not match"),
+      CartridgeError::HeaderChecksumMismatch { expected, actual } => {
+        write!(f, "header checksum is {:#04x}, expected {:#04x}", actual, expected)
+      }
+      CartridgeError::GlobalChecksumMismatch { expected, actual } => {
+        write!(f, "global checksum is {:#06x}, expected {:#06x}", actual, expected)
+      }
+      CartridgeError::UnsupportedMapper(mapper) => write!(f, "{:?} cartridges are not supported", mapper),
+    }
+  }
+}
+
+impl error::Error for CartridgeError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mapper {
+  RomOnly,
+  Mbc1,
+  Mbc2,
+  Mmm01,
+  Mbc3,
+  Mbc5,
+  Mbc6,
+  Mbc7,
+  PocketCamera,
+  Tama5,
+  HuC3,
+  HuC1,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CartridgeType {
+  pub mapper: Mapper,
+  pub ram: bool,
+  pub battery: bool,
+  pub timer: bool,
+  pub rumble: bool,
+}
+
+impl CartridgeType {
+  pub fn from_code(code: u8) -> Option<CartridgeType> {
+    let (mapper, ram, battery, timer, rumble) = match code {
+      0x00 => (Mapper::RomOnly, false, false, false, false),
+      0x01 => (Mapper::Mbc1, false, false, false, false),
+      0x02 => (Mapper::Mbc1, true, false, false, false),
+      0x03 => (Mapper::Mbc1, true, true, false, false),
+      0x05 => (Mapper::Mbc2, false, false, false, false),
+      0x06 => (Mapper::Mbc2, false, true, false, false),
+      0x08 => (Mapper::RomOnly, true, false, false, false),
+      0x09 => (Mapper::RomOnly, true, true, false, false),
+      0x0B => (Mapper::Mmm01, false, false, false, false),
+      0x0C => (Mapper::Mmm01, true, false, false, false),
+      0x0D => (Mapper::Mmm01, true, true, false, false),
+      0x0F => (Mapper::Mbc3, false, true, true, false),
+      0x10 => (Mapper::Mbc3, true, true, true, false),
+      0x11 => (Mapper::Mbc3, false, false, false, false),
+      0x12 => (Mapper::Mbc3, true, false, false, false),
+      0x13 => (Mapper::Mbc3, true, true, false, false),
+      0x19 => (Mapper::Mbc5, false, false, false, false),
+      0x1A => (Mapper::Mbc5, true, false, false, false),
+      0x1B => (Mapper::Mbc5, true, true, false, false),
+      0x1C => (Mapper::Mbc5, false, false, false, true),
+      0x1D => (Mapper::Mbc5, true, false, false, true),
+      0x1E => (Mapper::Mbc5, true, true, false, true),
+      0x20 => (Mapper::Mbc6, true, true, false, false),
+      0x22 => (Mapper::Mbc7, true, true, false, true),
+      0xFC => (Mapper::PocketCamera, true, true, false, false),
+      0xFD => (Mapper::Tama5, true, true, true, false),
+      0xFE => (Mapper::HuC3, true, true, true, false),
+      0xFF => (Mapper::HuC1, true, true, false, false),
+      _ => return None,
+    };
+
+    Some(CartridgeType {
+      mapper,
+      ram,
+      battery,
+      timer,
+      rumble,
+    })
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CgbSupport {
+  None,
+  Enhanced, // Runs on both DMG and CGB
+  Required,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Licensee {
+  Old(u8),
+  New(String),
+}
+
+pub struct CartridgeHeader {
+  pub title: String,
+  pub manufacturer_code: Option<String>,
+  pub cgb_support: CgbSupport,
+  pub licensee: Licensee,
+  pub sgb_support: bool,
+  pub cartridge_type: CartridgeType,
+  pub rom_size: usize, // Bytes
+  pub ram_size: usize, // Bytes
+  pub destination_code: u8,
+  pub version: u8,
+  pub header_checksum: u8,
+  pub global_checksum: u16,
+}
+
+impl CartridgeHeader {
+  // Parses the header at 0x0100-0x014F, checking that the ROM is as large as it claims to be
+  pub fn parse(rom: &[u8]) -> Result<CartridgeHeader, CartridgeError> {
+    CartridgeHeader::parse_at(rom, 0)
+  }
+
+  // Parses the header of the 32 KiB starting at `base` instead, where MMM01 multicarts keep theirs
+  pub fn parse_at(rom: &[u8], base: usize) -> Result<CartridgeHeader, CartridgeError> {
+    if rom.len() < base + HEADER_END {
+      return Err(CartridgeError::Truncated(rom.len()));
+    }
+
+    let header = &rom[base..];
+
+    let cartridge_type = match CartridgeType::from_code(header[CARTRIDGE_TYPE]) {
+      Some(cartridge_type) => cartridge_type,
+      None => return Err(CartridgeError::InvalidCartridgeType(header[CARTRIDGE_TYPE])),
+    };
+
+    let rom_size = match header[ROM_SIZE] {
+      code @ 0x00 ..= 0x08 => (ROM_BANK_SIZE * 2) << code,
+      code => return Err(CartridgeError::InvalidRomSize(code)),
+    };
+
+    let ram_size = match header[RAM_SIZE] {
+      0x00 => 0,
+      0x01 => 0x0800,
+      0x02 => 0x2000,
+      0x03 => 0x8000,
+      0x04 => 0x20000,
+      0x05 => 0x10000,
+      code => return Err(CartridgeError::InvalidRamSize(code)),
+    };
+
+    if rom.len() < rom_size {
+      return Err(CartridgeError::RomSizeMismatch { expected: rom_size, actual: rom.len() });
+    }
+
+    let cgb_support = match header[CGB_FLAG] {
+      0x80 => CgbSupport::Enhanced,
+      0xC0 => CgbSupport::Required,
+      _ => CgbSupport::None,
+    };
+
+    // Newer cartridges shortened the title to make room for the CGB flag and a manufacturer code,
+    // though plenty of CGB ones still run their title up to the flag instead
+    let code = &header[MANUFACTURER_CODE_START..MANUFACTURER_CODE_END];
+    let (title, manufacturer_code) = match cgb_support {
+      CgbSupport::None => (read_string(&header[TITLE_START..TITLE_END]), None),
+      _ if code.iter().all(|byte| byte.is_ascii_alphanumeric()) => {
+        (read_string(&header[TITLE_START..CGB_TITLE_END]), Some(read_string(code)))
+      }
+      _ => (read_string(&header[TITLE_START..CGB_FLAG]), None),
+    };
+
+    let licensee = match header[OLD_LICENSEE_CODE] {
+      USE_NEW_LICENSEE_CODE => Licensee::New(read_string(&header[NEW_LICENSEE_CODE_START..NEW_LICENSEE_CODE_END])),
+      code => Licensee::Old(code),
+    };
+
+    Ok(CartridgeHeader {
+      title,
+      manufacturer_code,
+      cgb_support,
+      licensee,
+      sgb_support: header[SGB_FLAG] == 0x03,
+      cartridge_type,
+      rom_size,
+      ram_size,
+      destination_code: header[DESTINATION_CODE],
+      version: header[VERSION],
+      header_checksum: header[HEADER_CHECKSUM],
+      global_checksum: BigEndian::read_u16(&header[GLOBAL_CHECKSUM..]),
+    })
+  }
+
+  // The boot ROM refuses to start a cartridge unless its logo matches exactly
+  pub fn verify_logo(&self, rom: &[u8]) -> Result<(), CartridgeError> {
+    if rom[LOGO_START..LOGO_END] == NINTENDO_LOGO[..] {
+      Ok(())
+    } else {
+      Err(CartridgeError::InvalidLogo)
+    }
+  }
+
+  // Checked by the boot ROM, a mismatch locks up the console
+  pub fn verify_header_checksum(&self, rom: &[u8]) -> Result<(), CartridgeError> {
+    let checksum = rom[HEADER_CHECKSUM_START..HEADER_CHECKSUM].iter()
+      .fold(0u8, |checksum, &byte| checksum.wrapping_sub(byte).wrapping_sub(1));
+
+    if checksum == self.header_checksum {
+      Ok(())
+    } else {
+      Err(CartridgeError::HeaderChecksumMismatch { expected: checksum, actual: self.header_checksum })
+    }
+  }
+
+  // Never checked by the hardware, so plenty of homebrew and test ROMs get it wrong
+  pub fn verify_global_checksum(&self, rom: &[u8]) -> Result<(), CartridgeError> {
+    let checksum = rom.iter().enumerate()
+      .filter(|&(address, _)| address != GLOBAL_CHECKSUM && address != GLOBAL_CHECKSUM + 1)
+      .fold(0u16, |checksum, (_, &byte)| checksum.wrapping_add(byte as u16));
+
+    if checksum == self.global_checksum {
+      Ok(())
+    } else {
+      Err(CartridgeError::GlobalChecksumMismatch { expected: checksum, actual: self.global_checksum })
+    }
+  }
+}
+
+impl fmt::Debug for CartridgeHeader {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    writeln!(f, "Cartridge Header")?;
+    writeln!(f, "  Title: {}", self.title)?;
+    writeln!(f, "  Manufacturer: {:?}", self.manufacturer_code)?;
+    writeln!(f, "  CGB: {:?}", self.cgb_support)?;
+    writeln!(f, "  SGB: {}", self.sgb_support)?;
+    writeln!(f, "  Licensee: {:?}", self.licensee)?;
+    writeln!(f, "  Type: {:?}", self.cartridge_type)?;
+    writeln!(f, "  ROM: {} KiB", self.rom_size / 1024)?;
+    writeln!(f, "  RAM: {} KiB", self.ram_size / 1024)?;
+    writeln!(f, "  Destination: {:#04x}", self.destination_code)?;
+    writeln!(f, "  Version: {:#04x}", self.version)?;
+    writeln!(f, "  Header Checksum: {:#04x}", self.header_checksum)?;
+    writeln!(f, "  Global Checksum: {:#06x}", self.global_checksum)
+  }
+}
+
+fn read_string(bytes: &[u8]) -> String {
+  let end = bytes.iter().position(|&byte| byte == 0).unwrap_or(bytes.len());
+
+  String::from_utf8_lossy(&bytes[..end]).trim_end().to_string()
+}
+
+#[cfg(test)]
+pub mod tests {
+  use super::*;
+
+  // A ROM of the given type and size with a valid logo and header checksum
+  pub fn rom(cartridge_type: u8, rom_size_code: u8, ram_size_code: u8) -> Vec<u8> {
+    let mut rom = vec![0; (ROM_BANK_SIZE * 2) << rom_size_code];
+
+    rom[LOGO_START..LOGO_END].copy_from_slice(&NINTENDO_LOGO);
+    rom[TITLE_START..TITLE_START + 4].copy_from_slice(b"TEST");
+    rom[CARTRIDGE_TYPE] = cartridge_type;
+    rom[ROM_SIZE] = rom_size_code;
+    rom[RAM_SIZE] = ram_size_code;
+    fix_header_checksum(&mut rom);
+
+    rom
+  }
+
+  pub fn fix_header_checksum(rom: &mut [u8]) {
+    rom[HEADER_CHECKSUM] = rom[HEADER_CHECKSUM_START..HEADER_CHECKSUM].iter()
+      .fold(0u8, |checksum, &byte| checksum.wrapping_sub(byte).wrapping_sub(1));
+  }
+
+  #[test]
+  fn parses_a_valid_header() {
+    let rom = rom(0x03, 0x00, 0x02);
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    assert_eq!(header.title, "TEST");
+    assert_eq!(header.manufacturer_code, None);
+    assert_eq!(header.cgb_support, CgbSupport::None);
+    assert_eq!(header.cartridge_type, CartridgeType::from_code(0x03).unwrap());
+    assert_eq!(header.rom_size, 0x8000);
+    assert_eq!(header.ram_size, 0x2000);
+    assert_eq!(header.verify_logo(&rom), Ok(()));
+    assert_eq!(header.verify_header_checksum(&rom), Ok(()));
+  }
+
+  #[test]
+  fn rejects_a_truncated_rom() {
+    let rom = rom(0x00, 0x00, 0x00);
+
+    assert_eq!(CartridgeHeader::parse(&rom[..0x014F]).err(), Some(CartridgeError::Truncated(0x014F)));
+  }
+
+  #[test]
+  fn rejects_an_unknown_rom_size() {
+    let mut rom = rom(0x00, 0x00, 0x00);
+
+    rom[ROM_SIZE] = 0x09;
+
+    assert_eq!(CartridgeHeader::parse(&rom).err(), Some(CartridgeError::InvalidRomSize(0x09)));
+  }
+
+  #[test]
+  fn rejects_a_rom_smaller_than_its_header_claims() {
+    let mut rom = rom(0x01, 0x00, 0x00);
+
+    rom[ROM_SIZE] = 0x01;
+
+    assert_eq!(
+      CartridgeHeader::parse(&rom).err(),
+      Some(CartridgeError::RomSizeMismatch { expected: 0x10000, actual: 0x8000 })
+    );
+  }
+
+  #[test]
+  fn reports_a_header_checksum_mismatch() {
+    let mut rom = rom(0x00, 0x00, 0x00);
+    let expected = rom[HEADER_CHECKSUM];
+
+    rom[HEADER_CHECKSUM] = expected.wrapping_add(1);
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    assert_eq!(
+      header.verify_header_checksum(&rom),
+      Err(CartridgeError::HeaderChecksumMismatch { expected, actual: expected.wrapping_add(1) })
+    );
+  }
+
+  #[test]
+  fn reports_an_invalid_logo() {
+    let mut rom = rom(0x00, 0x00, 0x00);
+
+    rom[LOGO_START] = 0x00;
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    assert_eq!(header.verify_logo(&rom), Err(CartridgeError::InvalidLogo));
+  }
+
+  #[test]
+  fn splits_cgb_title_and_manufacturer_code() {
+    let mut rom = rom(0x00, 0x00, 0x00);
+
+    rom[TITLE_START..CGB_TITLE_END].copy_from_slice(b"POKEMON_GLD");
+    rom[MANUFACTURER_CODE_START..MANUFACTURER_CODE_END].copy_from_slice(b"AAUE");
+    rom[CGB_FLAG] = 0x80;
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    assert_eq!(header.title, "POKEMON_GLD");
+    assert_eq!(header.manufacturer_code, Some("AAUE".to_string()));
+    assert_eq!(header.cgb_support, CgbSupport::Enhanced);
+
+    // Anything but four alphanumeric characters is part of the title rather than a code
+    rom[TITLE_START..CGB_TITLE_END].copy_from_slice(b"ZELDA DX\0\0\0");
+    rom[MANUFACTURER_CODE_START..MANUFACTURER_CODE_END].copy_from_slice(b"\0\0\0\0");
+    rom[CGB_FLAG] = 0xC0;
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    assert_eq!(header.title, "ZELDA DX");
+    assert_eq!(header.manufacturer_code, None);
+    assert_eq!(header.cgb_support, CgbSupport::Required);
+
+    rom[TITLE_START..CGB_FLAG].copy_from_slice(b"POKEMON YELLOW\0");
+    rom[CGB_FLAG] = 0x80;
+
+    let header = CartridgeHeader::parse(&rom).unwrap();
+
+    assert_eq!(header.title, "POKEMON YELLOW");
+    assert_eq!(header.manufacturer_code, None);
+  }
+}
@@ -0,0 +1,66 @@
+use super::{MemoryBankController, read_rom_bank, ram_offset, IR_NO_LIGHT};
+
+const IR_MODE: u8 = 0x0E;
+
+pub struct HuC1 {
+  ir_mode: bool, // 0xA000-0xBFFF maps the infrared port instead of RAM
+  rom_bank: u8, // 6 bits
+  ram_bank: u8, // 2 bits
+}
+
+impl HuC1 {
+  pub fn new() -> HuC1 {
+    HuC1 {
+      ir_mode: false,
+      rom_bank: 1,
+      ram_bank: 0,
+    }
+  }
+}
+
+impl MemoryBankController for HuC1 {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+    let bank = match address {
+      0x0000 ..= 0x3FFF => 0,
+      _ => self.rom_bank as usize,
+    };
+
+    read_rom_bank(rom, bank, address)
+  }
+
+  fn write_register(&mut self, address: u16, value: u8) {
+    match address {
+      0x0000 ..= 0x1FFF => self.ir_mode = (value & 0x0F) == IR_MODE,
+      0x2000 ..= 0x3FFF => {
+        self.rom_bank = match value & 0x3F {
+          0 => 1,
+          bank => bank,
+        };
+      }
+      0x4000 ..= 0x5FFF => self.ram_bank = value & 0x03,
+      _ => {}
+    }
+  }
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+    if self.ir_mode {
+      return IR_NO_LIGHT;
+    }
+
+    match ram_offset(ram, self.ram_bank as usize, address) {
+      Some(offset) => ram[offset],
+      None => 0xFF,
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) {
+    // Writes in IR mode drive the infrared LED, which nothing is watching
+    if self.ir_mode {
+      return;
+    }
+
+    if let Some(offset) = ram_offset(ram, self.ram_bank as usize, address) {
+      ram[offset] = value;
+    }
+  }
+}
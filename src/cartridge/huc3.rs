@@ -0,0 +1,172 @@
+use super::{MemoryBankController, read_rom_bank, ram_offset, IR_NO_LIGHT};
+use super::real_time_clock::{TimeSource, ClockSource};
+
+// Values written to 0x0000-0x1FFF select what 0xA000-0xBFFF maps
+const MODE_RAM_READ: u8 = 0x00;
+const MODE_RAM_WRITE: u8 = 0x0A;
+const MODE_RTC_COMMAND: u8 = 0x0B;
+const MODE_RTC_RESPONSE: u8 = 0x0C;
+const MODE_RTC_SEMAPHORE: u8 = 0x0D;
+const MODE_IR: u8 = 0x0E;
+
+// RTC commands, sent in the upper nibble with an argument in the lower one
+const COMMAND_READ: u8 = 0x1;
+const COMMAND_WRITE: u8 = 0x2;
+const COMMAND_WRITE_AND_INCREMENT: u8 = 0x3;
+const COMMAND_ADDRESS_LOW: u8 = 0x4;
+const COMMAND_ADDRESS_HIGH: u8 = 0x5;
+
+// The clock keeps the minute of the day and a day counter, 12 bits each
+const MINUTES_PER_DAY: u16 = 24 * 60;
+const DAYS_MASK: u16 = 0x0FFF;
+
+pub struct HuC3 {
+  mode: u8,
+  rom_bank: u8, // 7 bits
+  ram_bank: u8, // 2 bits
+  address: u8, // Nibble of RTC memory the next command accesses
+  response: u8, // Nibble returned by the last read command
+  memory: [u8; 0x100], // RTC scratch nibbles beyond the time
+  minutes: u16,
+  days: u16,
+  seconds: u64, // Elapsed seconds not yet counted as a minute
+  time: TimeSource,
+}
+
+impl HuC3 {
+  pub fn new(source: ClockSource) -> HuC3 {
+    HuC3 {
+      mode: MODE_RAM_READ,
+      rom_bank: 1,
+      ram_bank: 0,
+      address: 0,
+      response: 0,
+      memory: [0; 0x100],
+      minutes: 0,
+      days: 0,
+      seconds: 0,
+      time: TimeSource::new(source),
+    }
+  }
+
+  fn update(&mut self) {
+    self.seconds += self.time.elapsed();
+
+    let minutes = self.minutes as u64 + self.seconds / 60;
+
+    self.seconds %= 60;
+    self.minutes = (minutes % MINUTES_PER_DAY as u64) as u16;
+    self.days = ((self.days as u64 + minutes / MINUTES_PER_DAY as u64) & DAYS_MASK as u64) as u16;
+  }
+
+  // Addresses 0-2 hold the minutes and 3-5 the days, least significant nibble first
+  fn read_nibble(&self, address: u8) -> u8 {
+    match address {
+      0 ..= 2 => ((self.minutes >> (address * 4)) & 0x0F) as u8,
+      3 ..= 5 => ((self.days >> ((address - 3) * 4)) & 0x0F) as u8,
+      _ => self.memory[address as usize],
+    }
+  }
+
+  fn write_nibble(&mut self, address: u8, value: u8) {
+    let value = (value & 0x0F) as u16;
+
+    match address {
+      0 ..= 2 => {
+        let shift = address * 4;
+
+        self.minutes = (self.minutes & !(0x0F << shift)) | value << shift;
+        self.time.reset();
+        self.seconds = 0;
+      }
+      3 ..= 5 => {
+        let shift = (address - 3) * 4;
+
+        self.days = (self.days & !(0x0F << shift)) | value << shift;
+      }
+      _ => self.memory[address as usize] = value as u8,
+    }
+  }
+
+  fn execute(&mut self, command: u8) {
+    let argument = command & 0x0F;
+
+    self.update();
+
+    match command >> 4 {
+      COMMAND_READ => {
+        self.response = self.read_nibble(self.address);
+        self.address = self.address.wrapping_add(1);
+      }
+      COMMAND_WRITE => {
+        let address = self.address;
+
+        self.write_nibble(address, argument);
+      }
+      COMMAND_WRITE_AND_INCREMENT => {
+        let address = self.address;
+
+        self.write_nibble(address, argument);
+        self.address = self.address.wrapping_add(1);
+      }
+      COMMAND_ADDRESS_LOW => self.address = (self.address & 0xF0) | argument,
+      COMMAND_ADDRESS_HIGH => self.address = (self.address & 0x0F) | argument << 4,
+      _ => {}
+    }
+  }
+}
+
+impl MemoryBankController for HuC3 {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+    let bank = match address {
+      0x0000 ..= 0x3FFF => 0,
+      _ => self.rom_bank as usize,
+    };
+
+    read_rom_bank(rom, bank, address)
+  }
+
+  fn write_register(&mut self, address: u16, value: u8) {
+    match address {
+      0x0000 ..= 0x1FFF => self.mode = value & 0x0F,
+      0x2000 ..= 0x3FFF => {
+        self.rom_bank = match value & 0x7F {
+          0 => 1,
+          bank => bank,
+        };
+      }
+      0x4000 ..= 0x5FFF => self.ram_bank = value & 0x03,
+      _ => {}
+    }
+  }
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+    match self.mode {
+      MODE_RAM_READ | MODE_RAM_WRITE => match ram_offset(ram, self.ram_bank as usize, address) {
+        Some(offset) => ram[offset],
+        None => 0xFF,
+      },
+      MODE_RTC_RESPONSE => 0x80 | self.response,
+      // Commands complete instantly, so the clock is always ready
+      MODE_RTC_SEMAPHORE => 0x01,
+      MODE_IR => IR_NO_LIGHT,
+      _ => 0xFF,
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) {
+    match self.mode {
+      MODE_RAM_WRITE => {
+        if let Some(offset) = ram_offset(ram, self.ram_bank as usize, address) {
+          ram[offset] = value;
+        }
+      }
+      MODE_RTC_COMMAND => self.execute(value),
+      _ => {}
+    }
+  }
+
+  fn tick(&mut self, cycles: u32) {
+    self.time.tick(cycles);
+  }
+}
@@ -0,0 +1,175 @@
+use super::{MemoryBankController, read_rom_bank, ram_offset};
+use super::header::NINTENDO_LOGO;
+
+const MULTICART_SIZE: usize = 0x100000;
+const MULTICART_LOGO_START: usize = 0x40104;
+
+pub struct Mbc1 {
+  ram_enabled: bool,
+  rom_bank: u8, // BANK1, 5 bits
+  bank: u8, // BANK2, 2 bits of either RAM bank or upper ROM bank
+  advanced_banking: bool, // Mode select, BANK2 also applies to 0x0000-0x3FFF and RAM
+  multicart: bool, // MBC1M, BANK2 is wired one bit lower
+}
+
+impl Mbc1 {
+  pub fn new(multicart: bool) -> Mbc1 {
+    Mbc1 {
+      ram_enabled: false,
+      rom_bank: 1,
+      bank: 0,
+      advanced_banking: false,
+      multicart,
+    }
+  }
+
+  // MBC1M collections are 1 MiB and repeat the boot logo at the start of each 256 KiB game
+  pub fn is_multicart(rom: &[u8]) -> bool {
+    rom.len() == MULTICART_SIZE &&
+    rom[MULTICART_LOGO_START..MULTICART_LOGO_START + NINTENDO_LOGO.len()] == NINTENDO_LOGO[..]
+  }
+
+  fn upper_rom_bank(&self) -> usize {
+    let shift = if self.multicart { 4 } else { 5 };
+
+    (self.bank as usize) << shift
+  }
+
+  fn lower_rom_bank(&self) -> usize {
+    let mask = if self.multicart { 0x0F } else { 0x1F };
+
+    (self.rom_bank & mask) as usize
+  }
+
+  fn ram_bank(&self) -> usize {
+    if self.advanced_banking { self.bank as usize } else { 0 }
+  }
+}
+
+impl MemoryBankController for Mbc1 {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+    let bank = match address {
+      0x0000 ..= 0x3FFF if self.advanced_banking => self.upper_rom_bank(),
+      0x0000 ..= 0x3FFF => 0,
+      _ => self.upper_rom_bank() | self.lower_rom_bank(),
+    };
+
+    read_rom_bank(rom, bank, address)
+  }
+
+  fn write_register(&mut self, address: u16, value: u8) {
+    match address {
+      0x0000 ..= 0x1FFF => self.ram_enabled = (value & 0x0F) == 0x0A,
+      0x2000 ..= 0x3FFF => {
+        // Bank 0 can't be selected here, only the full 5-bit value is checked
+        self.rom_bank = match value & 0x1F {
+          0 => 1,
+          bank => bank,
+        };
+      }
+      0x4000 ..= 0x5FFF => self.bank = value & 0x03,
+      _ => self.advanced_banking = (value & 0x01) != 0,
+    }
+  }
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+    match ram_offset(ram, self.ram_bank(), address) {
+      Some(offset) if self.ram_enabled => ram[offset],
+      _ => 0xFF,
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) {
+    if let Some(offset) = ram_offset(ram, self.ram_bank(), address) {
+      if self.ram_enabled {
+        ram[offset] = value;
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::banked_rom as rom;
+
+  #[test]
+  fn bank_zero_selects_bank_one() {
+    let rom = rom(128);
+    let mut mbc = Mbc1::new(false);
+
+    mbc.write_register(0x2000, 0x00);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+    // Only the five bits written are checked, so 0x20 also becomes 0x21 with BANK2 set
+    mbc.write_register(0x4000, 0x01);
+    mbc.write_register(0x2000, 0x20);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x21);
+
+    mbc.write_register(0x2000, 0x05);
+    assert_eq!(mbc.read_rom(&rom, 0x7FFF), 0x25);
+  }
+
+  #[test]
+  fn mode_one_banks_the_lower_area() {
+    let rom = rom(128);
+    let mut mbc = Mbc1::new(false);
+
+    mbc.write_register(0x4000, 0x02);
+    assert_eq!(mbc.read_rom(&rom, 0x0000), 0x00);
+
+    mbc.write_register(0x6000, 0x01);
+    assert_eq!(mbc.read_rom(&rom, 0x0000), 0x40);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x41);
+
+    mbc.write_register(0x6000, 0x00);
+    assert_eq!(mbc.read_rom(&rom, 0x3FFF), 0x00);
+  }
+
+  #[test]
+  fn mode_one_banks_ram() {
+    let mut ram = vec![0; 0x8000];
+    let mut mbc = Mbc1::new(false);
+
+    mbc.write_register(0x0000, 0x0A);
+    mbc.write_register(0x4000, 0x03);
+    mbc.write_ram(&mut ram, 0x0000, 0x11);
+    assert_eq!(ram[0x0000], 0x11);
+
+    mbc.write_register(0x6000, 0x01);
+    mbc.write_ram(&mut ram, 0x0000, 0x33);
+    assert_eq!(ram[0x6000], 0x33);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 0x33);
+
+    mbc.write_register(0x0000, 0x00);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+  }
+
+  #[test]
+  fn multicart_wires_bank2_one_bit_lower() {
+    let rom = rom(64);
+    let mut mbc = Mbc1::new(true);
+
+    mbc.write_register(0x4000, 0x03);
+    mbc.write_register(0x2000, 0x1F);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x3F);
+
+    // Bit 4 of BANK1 isn't connected, yet still counts towards the zero check
+    mbc.write_register(0x2000, 0x10);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x30);
+
+    mbc.write_register(0x6000, 0x01);
+    assert_eq!(mbc.read_rom(&rom, 0x0000), 0x30);
+  }
+
+  #[test]
+  fn detects_multicarts_by_their_second_logo() {
+    let mut rom = rom(64);
+
+    assert!(!Mbc1::is_multicart(&rom));
+
+    rom[MULTICART_LOGO_START..MULTICART_LOGO_START + NINTENDO_LOGO.len()].copy_from_slice(&NINTENDO_LOGO);
+    assert!(Mbc1::is_multicart(&rom));
+    assert!(!Mbc1::is_multicart(&rom[..0x80000]));
+  }
+}
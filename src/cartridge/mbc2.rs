@@ -0,0 +1,100 @@
+use super::{MemoryBankController, read_rom_bank};
+
+// 512 half-bytes of RAM are built into the MBC2 itself
+pub const RAM_SIZE: usize = 0x200;
+
+pub struct Mbc2 {
+  ram_enabled: bool,
+  rom_bank: u8, // 4 bits
+}
+
+impl Mbc2 {
+  pub fn new() -> Mbc2 {
+    Mbc2 {
+      ram_enabled: false,
+      rom_bank: 1,
+    }
+  }
+}
+
+impl MemoryBankController for Mbc2 {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+    let bank = match address {
+      0x0000 ..= 0x3FFF => 0,
+      _ => self.rom_bank as usize,
+    };
+
+    read_rom_bank(rom, bank, address)
+  }
+
+  fn write_register(&mut self, address: u16, value: u8) {
+    // Both registers share 0x0000-0x3FFF and are told apart by address bit 8
+    match address {
+      0x0000 ..= 0x3FFF if (address & 0x0100) == 0 => self.ram_enabled = (value & 0x0F) == 0x0A,
+      0x0000 ..= 0x3FFF => {
+        self.rom_bank = match value & 0x0F {
+          0 => 1,
+          bank => bank,
+        };
+      }
+      _ => {}
+    }
+  }
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+    if self.ram_enabled {
+      // Only the lower nibble exists, the upper one is open bus
+      ram[address as usize % RAM_SIZE] | 0xF0
+    } else {
+      0xFF
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) {
+    if self.ram_enabled {
+      ram[address as usize % RAM_SIZE] = value & 0x0F;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::banked_rom as rom;
+
+  #[test]
+  fn address_bit_8_picks_the_register() {
+    let rom = rom(16);
+    let mut ram = vec![0; RAM_SIZE];
+    let mut mbc = Mbc2::new();
+
+    // Bit 8 clear enables RAM, whatever else the address is
+    mbc.write_register(0x3EFF, 0x0A);
+    mbc.write_ram(&mut ram, 0x0000, 0x05);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 0xF5);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 1);
+
+    // Bit 8 set selects the ROM bank instead
+    mbc.write_register(0x0100, 0x0A);
+    assert_eq!(mbc.read_rom(&rom, 0x4000), 0x0A);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 0xF5);
+
+    mbc.write_register(0x3F00, 0x00);
+    assert_eq!(mbc.read_rom(&rom, 0x7FFF), 1);
+
+    mbc.write_register(0x2000, 0x00);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 0xFF);
+  }
+
+  #[test]
+  fn ram_is_512_half_bytes_repeated() {
+    let mut ram = vec![0; RAM_SIZE];
+    let mut mbc = Mbc2::new();
+
+    mbc.write_register(0x0000, 0x0A);
+    mbc.write_ram(&mut ram, 0x0201, 0xAB);
+
+    assert_eq!(ram[0x0001], 0x0B);
+    assert_eq!(mbc.read_ram(&ram, 0x1E01), 0xFB);
+  }
+}
@@ -0,0 +1,145 @@
+use super::{MemoryBankController, read_rom_bank, ram_offset};
+use super::real_time_clock::{RealTimeClock, ClockSource};
+
+pub struct Mbc3 {
+  ram_enabled: bool, // Also enables access to the RTC
+  rom_bank: u8, // 7 bits
+  ram_bank: u8, // 0x00-0x03 selects a RAM bank, 0x08-0x0C an RTC register
+  latch: u8, // Last value written to the latch register
+  clock: Option<RealTimeClock>,
+}
+
+impl Mbc3 {
+  pub fn new(timer: bool, source: ClockSource) -> Mbc3 {
+    Mbc3 {
+      ram_enabled: false,
+      rom_bank: 1,
+      ram_bank: 0,
+      latch: 0xFF,
+      clock: if timer { Some(RealTimeClock::new(source)) } else { None },
+    }
+  }
+}
+
+impl MemoryBankController for Mbc3 {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+    let bank = match address {
+      0x0000 ..= 0x3FFF => 0,
+      _ => self.rom_bank as usize,
+    };
+
+    read_rom_bank(rom, bank, address)
+  }
+
+  fn write_register(&mut self, address: u16, value: u8) {
+    match address {
+      0x0000 ..= 0x1FFF => self.ram_enabled = (value & 0x0F) == 0x0A,
+      0x2000 ..= 0x3FFF => {
+        self.rom_bank = match value & 0x7F {
+          0 => 1,
+          bank => bank,
+        };
+      }
+      0x4000 ..= 0x5FFF => self.ram_bank = value & 0x0F,
+      _ => {
+        // Writing 0x00 followed by 0x01 latches the current time
+        if self.latch == 0x00 && value == 0x01 {
+          if let Some(ref mut clock) = self.clock {
+            clock.latch();
+          }
+        }
+
+        self.latch = value;
+      }
+    }
+  }
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+    if !self.ram_enabled {
+      return 0xFF;
+    }
+
+    match (self.ram_bank, &self.clock) {
+      (0x00 ..= 0x03, _) => match ram_offset(ram, self.ram_bank as usize, address) {
+        Some(offset) => ram[offset],
+        None => 0xFF,
+      },
+      (0x08 ..= 0x0C, Some(clock)) => clock.read_register(self.ram_bank),
+      _ => 0xFF,
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) {
+    if !self.ram_enabled {
+      return;
+    }
+
+    match (self.ram_bank, &mut self.clock) {
+      (0x00 ..= 0x03, _) => {
+        if let Some(offset) = ram_offset(ram, self.ram_bank as usize, address) {
+          ram[offset] = value;
+        }
+      }
+      (0x08 ..= 0x0C, Some(clock)) => clock.write_register(self.ram_bank, value),
+      _ => {}
+    }
+  }
+
+  fn save_battery(&mut self) -> Vec<u8> {
+    match self.clock {
+      Some(ref mut clock) => clock.save(),
+      None => Vec::new(),
+    }
+  }
+
+  fn load_battery(&mut self, data: &[u8]) {
+    if let Some(ref mut clock) = self.clock {
+      clock.load(data);
+    }
+  }
+
+  fn tick(&mut self, cycles: u32) {
+    if let Some(ref mut clock) = self.clock {
+      clock.tick(cycles);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // The RTC's 32768 Hz crystal, in T-cycles
+  const CYCLES_PER_SECOND: u32 = 4194304;
+
+  #[test]
+  fn latches_on_zero_then_one() {
+    let mut ram = vec![0; 0x2000];
+    let mut mbc = Mbc3::new(true, ClockSource::Emulated);
+
+    mbc.write_register(0x0000, 0x0A);
+    mbc.write_register(0x4000, 0x08);
+    mbc.tick(2 * CYCLES_PER_SECOND);
+
+    mbc.write_register(0x6000, 0x01);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 0);
+
+    mbc.write_register(0x6000, 0x00);
+    mbc.write_register(0x6000, 0x01);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 2);
+
+    // Latched values hold until the next latch
+    mbc.tick(CYCLES_PER_SECOND);
+    mbc.write_register(0x6000, 0x01);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 2);
+
+    mbc.write_register(0x6000, 0x00);
+    mbc.write_register(0x6000, 0x01);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 3);
+
+    // RAM banks are still there alongside the clock
+    mbc.write_register(0x4000, 0x01);
+    mbc.write_ram(&mut ram, 0x0000, 0x42);
+    assert_eq!(mbc.read_ram(&ram, 0x0000), 0x42);
+  }
+}
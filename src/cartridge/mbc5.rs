@@ -0,0 +1,128 @@
+use super::{MemoryBankController, read_rom_bank, ram_offset};
+
+const RUMBLE_MOTOR: u8 = 0x08;
+
+pub struct Mbc5 {
+  ram_enabled: bool,
+  rom_bank: u16, // 9 bits, bank 0 can be selected
+  ram_bank: u8, // 4 bits
+  has_rumble: bool, // Bit 3 of the RAM bank register drives the motor instead
+  motor: bool,
+}
+
+impl Mbc5 {
+  pub fn new(has_rumble: bool) -> Mbc5 {
+    Mbc5 {
+      ram_enabled: false,
+      rom_bank: 1,
+      ram_bank: 0,
+      has_rumble,
+      motor: false,
+    }
+  }
+}
+
+impl MemoryBankController for Mbc5 {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+    let bank = match address {
+      0x0000 ..= 0x3FFF => 0,
+      _ => self.rom_bank as usize,
+    };
+
+    read_rom_bank(rom, bank, address)
+  }
+
+  fn write_register(&mut self, address: u16, value: u8) {
+    match address {
+      0x0000 ..= 0x1FFF => self.ram_enabled = (value & 0x0F) == 0x0A,
+      0x2000 ..= 0x2FFF => self.rom_bank = (self.rom_bank & 0x100) | value as u16,
+      0x3000 ..= 0x3FFF => self.rom_bank = (self.rom_bank & 0xFF) | ((value & 0x01) as u16) << 8,
+      0x4000 ..= 0x5FFF => {
+        if self.has_rumble {
+          self.motor = (value & RUMBLE_MOTOR) != 0;
+          self.ram_bank = value & 0x07;
+        } else {
+          self.ram_bank = value & 0x0F;
+        }
+      }
+      _ => {}
+    }
+  }
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+    match ram_offset(ram, self.ram_bank as usize, address) {
+      Some(offset) if self.ram_enabled => ram[offset],
+      _ => 0xFF,
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) {
+    if let Some(offset) = ram_offset(ram, self.ram_bank as usize, address) {
+      if self.ram_enabled {
+        ram[offset] = value;
+      }
+    }
+  }
+
+  fn rumble(&self) -> bool {
+    self.motor
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::banked_rom as rom;
+
+  fn bank(mbc: &Mbc5, rom: &[u8]) -> u16 {
+    mbc.read_rom(rom, 0x4000) as u16 | (mbc.read_rom(rom, 0x4001) as u16) << 8
+  }
+
+  #[test]
+  fn selects_nine_bit_banks_including_zero() {
+    let rom = rom(512);
+    let mut mbc = Mbc5::new(false);
+
+    mbc.write_register(0x2000, 0x00);
+    assert_eq!(bank(&mbc, &rom), 0x000);
+
+    mbc.write_register(0x3000, 0x01);
+    assert_eq!(bank(&mbc, &rom), 0x100);
+
+    mbc.write_register(0x2000, 0xFF);
+    assert_eq!(bank(&mbc, &rom), 0x1FF);
+
+    mbc.write_register(0x3000, 0x00);
+    assert_eq!(bank(&mbc, &rom), 0x0FF);
+  }
+
+  #[test]
+  fn rumble_bit_drives_the_motor_instead_of_ram() {
+    let mut ram = vec![0; 0x20000];
+    let mut mbc = Mbc5::new(true);
+
+    mbc.write_register(0x0000, 0x0A);
+    mbc.write_register(0x4000, 0x0B);
+
+    assert!(mbc.rumble());
+
+    mbc.write_ram(&mut ram, 0x0000, 0x42);
+    assert_eq!(ram[3 * 0x2000], 0x42);
+
+    mbc.write_register(0x4000, 0x03);
+    assert!(!mbc.rumble());
+  }
+
+  #[test]
+  fn without_rumble_bit_3_banks_ram() {
+    let mut ram = vec![0; 0x20000];
+    let mut mbc = Mbc5::new(false);
+
+    mbc.write_register(0x0000, 0x0A);
+    mbc.write_register(0x4000, 0x0B);
+    mbc.write_ram(&mut ram, 0x0000, 0x42);
+
+    assert!(!mbc.rumble());
+    assert_eq!(ram[0x0B * 0x2000], 0x42);
+  }
+}
@@ -0,0 +1,118 @@
+use super::{MemoryBankController, read_rom_bank, ram_offset};
+use super::header::{CartridgeHeader, Mapper};
+
+// The multicart menu lives in the last 32 KiB, which every bank number wraps onto
+const MENU_BANK: usize = 0x1FE;
+pub const MENU_SIZE: usize = 0x8000;
+
+pub struct Mmm01 {
+  mapped: bool, // Once a game is mapped in the outer bank bits and masks are locked
+  ram_enabled: bool,
+  rom_bank: u16, // 9 bits
+  ram_bank: u8, // 4 bits
+  rom_bank_mask: u8, // Bits 1-4 of the ROM bank that the game can't change
+  ram_bank_mask: u8, // Bits 0-1 of the RAM bank that the game can't change
+  advanced_banking: bool, // MBC1 style mode select
+  mode_locked: bool,
+}
+
+impl Mmm01 {
+  pub fn new() -> Mmm01 {
+    Mmm01 {
+      mapped: false,
+      ram_enabled: false,
+      rom_bank: 0,
+      ram_bank: 0,
+      rom_bank_mask: 0,
+      ram_bank_mask: 0,
+      advanced_banking: false,
+      mode_locked: false,
+    }
+  }
+
+  // The menu carries the cartridge's real header, while bank 0 holds the first game's own
+  pub fn is_multicart(rom: &[u8]) -> bool {
+    rom.len() > MENU_SIZE && match CartridgeHeader::parse_at(rom, rom.len() - MENU_SIZE) {
+      Ok(header) => header.cartridge_type.mapper == Mapper::Mmm01,
+      Err(_) => false,
+    }
+  }
+
+  fn ram_bank(&self) -> usize {
+    if self.advanced_banking { self.ram_bank as usize } else { (self.ram_bank & 0x0C) as usize }
+  }
+}
+
+impl MemoryBankController for Mmm01 {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+    if !self.mapped {
+      return read_rom_bank(rom, MENU_BANK | (address >> 14) as usize, address);
+    }
+
+    let bank = match address {
+      // The bank at 0x0000 is the game's own bank 0, keeping only the fixed low bits
+      0x0000 ..= 0x3FFF => self.rom_bank & !0x1F | (self.rom_bank & self.rom_bank_mask as u16),
+      _ if (self.rom_bank & 0x1F) == 0 => self.rom_bank | 0x01,
+      _ => self.rom_bank,
+    };
+
+    read_rom_bank(rom, bank as usize, address)
+  }
+
+  fn write_register(&mut self, address: u16, value: u8) {
+    match address {
+      0x0000 ..= 0x1FFF => {
+        self.ram_enabled = (value & 0x0F) == 0x0A;
+
+        if !self.mapped {
+          self.ram_bank_mask = (value >> 4) & 0x03;
+          self.mapped = (value & 0x40) != 0;
+        }
+      }
+      0x2000 ..= 0x3FFF => {
+        if self.mapped {
+          let mask = self.rom_bank_mask as u16;
+
+          self.rom_bank = (self.rom_bank & (!0x1F | mask)) | (value as u16 & 0x1F & !mask);
+        } else {
+          self.rom_bank = (self.rom_bank & !0x7F) | (value & 0x7F) as u16;
+        }
+      }
+      0x4000 ..= 0x5FFF => {
+        let mask = self.ram_bank_mask;
+
+        self.ram_bank = (self.ram_bank & (0x0C | mask)) | (value & 0x03 & !mask);
+
+        if !self.mapped {
+          self.ram_bank = (self.ram_bank & 0x03) | (value & 0x0C);
+          self.rom_bank = (self.rom_bank & 0x7F) | ((value & 0x30) as u16) << 3;
+          self.mode_locked = (value & 0x40) != 0;
+        }
+      }
+      _ => {
+        if !self.mode_locked {
+          self.advanced_banking = (value & 0x01) != 0;
+        }
+
+        if !self.mapped {
+          self.rom_bank_mask = (value & 0x3C) >> 1;
+        }
+      }
+    }
+  }
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+    match ram_offset(ram, self.ram_bank(), address) {
+      Some(offset) if self.ram_enabled => ram[offset],
+      _ => 0xFF,
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) {
+    if let Some(offset) = ram_offset(ram, self.ram_bank(), address) {
+      if self.ram_enabled {
+        ram[offset] = value;
+      }
+    }
+  }
+}
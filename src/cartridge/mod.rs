@@ -0,0 +1,321 @@
+mod header;
+mod rom_only;
+mod mbc1;
+mod mbc2;
+mod mbc3;
+mod mbc5;
+mod mmm01;
+mod huc1;
+mod huc3;
+mod real_time_clock;
+
+pub use self::header::{CartridgeHeader, CartridgeError, Mapper};
+pub use self::real_time_clock::ClockSource;
+#[cfg(test)]
+pub use self::header::tests::rom as test_rom;
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use self::rom_only::RomOnly;
+use self::mbc1::Mbc1;
+use self::mbc2::Mbc2;
+use self::mbc3::Mbc3;
+use self::mbc5::Mbc5;
+use self::mmm01::Mmm01;
+use self::huc1::HuC1;
+use self::huc3::HuC3;
+
+const ROM_BANK_SIZE: usize = 0x4000;
+const RAM_BANK_SIZE: usize = 0x2000;
+
+// How long after the first unsaved write to external RAM the save file is flushed
+const SAVE_DELAY_CYCLES: u32 = 4194304;
+
+// Reading the infrared port of a HuC1 or HuC3 while no light is seen
+const IR_NO_LIGHT: u8 = 0xC0;
+
+// Decodes writes to the ROM area into bank switching and maps the banked
+// ROM (0x0000-0x7FFF) and external RAM (0xA000-0xBFFF, given as an offset)
+pub trait MemoryBankController {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8;
+  fn write_register(&mut self, address: u16, value: u8);
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8;
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8);
+
+  // Advances any hardware on the cartridge, such as a real-time clock
+  fn tick(&mut self, _cycles: u32) {}
+
+  // Whether a rumble motor on the cartridge is currently running
+  fn rumble(&self) -> bool { false }
+
+  // Battery-backed state kept in the save file after the RAM, such as a real-time clock
+  fn save_battery(&mut self) -> Vec<u8> { Vec::new() }
+  fn load_battery(&mut self, _data: &[u8]) {}
+}
+
+// Banks past the end of the ROM wrap around, as only the needed address lines are wired up
+fn read_rom_bank(rom: &[u8], bank: usize, address: u16) -> u8 {
+  rom[(bank * ROM_BANK_SIZE + (address as usize & (ROM_BANK_SIZE - 1))) % rom.len()]
+}
+
+fn ram_offset(ram: &[u8], bank: usize, address: u16) -> Option<usize> {
+  if ram.is_empty() {
+    None
+  } else {
+    Some((bank * RAM_BANK_SIZE + address as usize) % ram.len())
+  }
+}
+
+// Every byte of each bank holds its bank number, except the second byte which holds
+// the bits above the lowest 8
+#[cfg(test)]
+fn banked_rom(banks: usize) -> Vec<u8> {
+  let mut rom: Vec<u8> = (0..banks).flat_map(|bank| vec![bank as u8; ROM_BANK_SIZE]).collect();
+
+  for bank in 0..banks {
+    rom[bank * ROM_BANK_SIZE + 1] = (bank >> 8) as u8;
+  }
+
+  rom
+}
+
+pub struct Cartridge {
+  header: CartridgeHeader,
+  rom: Box<[u8]>,
+  ram: Box<[u8]>,
+  controller: Box<dyn MemoryBankController>,
+  save_file: Option<PathBuf>,
+  unsaved_cycles: Option<u32>, // Cycles since the first write not yet flushed to the save file
+}
+
+impl Cartridge {
+  pub fn new(rom: Box<[u8]>, clock_source: ClockSource) -> Result<Cartridge, CartridgeError> {
+    // MMM01 multicarts boot into a menu at the end of the ROM, so their header is there too
+    let base = if Mmm01::is_multicart(&rom) { rom.len() - mmm01::MENU_SIZE } else { 0 };
+    let header = CartridgeHeader::parse_at(&rom, base)?;
+
+    header.verify_logo(&rom[base..])?;
+    header.verify_header_checksum(&rom[base..])?;
+
+    let cartridge_type = header.cartridge_type;
+    let controller: Box<dyn MemoryBankController> = match cartridge_type.mapper {
+      Mapper::RomOnly => Box::new(RomOnly),
+      Mapper::Mbc1 => Box::new(Mbc1::new(Mbc1::is_multicart(&rom))),
+      Mapper::Mbc2 => Box::new(Mbc2::new()),
+      Mapper::Mbc3 => Box::new(Mbc3::new(cartridge_type.timer, clock_source)),
+      Mapper::Mbc5 => Box::new(Mbc5::new(cartridge_type.rumble)),
+      Mapper::Mmm01 => Box::new(Mmm01::new()),
+      Mapper::HuC1 => Box::new(HuC1::new()),
+      Mapper::HuC3 => Box::new(HuC3::new(clock_source)),
+      mapper => return Err(CartridgeError::UnsupportedMapper(mapper)),
+    };
+
+    // The MBC2's RAM is built in, so the header doesn't declare it
+    let ram_size = match cartridge_type.mapper {
+      Mapper::Mbc2 => mbc2::RAM_SIZE,
+      _ => header.ram_size,
+    };
+
+    let ram = vec![0; ram_size].into_boxed_slice();
+
+    Ok(Cartridge {
+      header,
+      rom,
+      ram,
+      controller,
+      save_file: None,
+      unsaved_cycles: None,
+    })
+  }
+
+  pub fn header(&self) -> &CartridgeHeader {
+    &self.header
+  }
+
+  pub fn rom(&self) -> &[u8] {
+    &self.rom
+  }
+
+  pub fn read_rom(&self, address: u16) -> u8 {
+    self.controller.read_rom(&self.rom, address)
+  }
+
+  pub fn write_rom(&mut self, address: u16, value: u8) {
+    self.controller.write_register(address, value);
+  }
+
+  pub fn read_ram(&self, address: u16) -> u8 {
+    self.controller.read_ram(&self.ram, address)
+  }
+
+  pub fn write_ram(&mut self, address: u16, value: u8) {
+    self.controller.write_ram(&mut self.ram, address, value);
+
+    if self.save_file.is_some() && self.unsaved_cycles.is_none() {
+      self.unsaved_cycles = Some(0);
+    }
+  }
+
+  pub fn tick(&mut self, cycles: u32) {
+    self.controller.tick(cycles);
+
+    if let Some(unsaved_cycles) = self.unsaved_cycles {
+      if unsaved_cycles + cycles >= SAVE_DELAY_CYCLES {
+        if let Err(error) = self.flush() {
+          eprintln!("Failed to write save file: {}", error);
+        }
+      } else {
+        self.unsaved_cycles = Some(unsaved_cycles + cycles);
+      }
+    }
+  }
+
+  // Keeps battery-backed RAM in a save file, loading it first if it exists.
+  // Cartridges without a battery lose their RAM on power off, so they are left alone
+  pub fn attach_save_file<P: Into<PathBuf>>(&mut self, path: P) -> io::Result<()> {
+    if !self.header.cartridge_type.battery {
+      return Ok(());
+    }
+
+    let path = path.into();
+
+    match fs::read(&path) {
+      Ok(data) => self.load_save(&data),
+      Err(ref error) if error.kind() == io::ErrorKind::NotFound => {}
+      Err(error) => return Err(error),
+    }
+
+    self.save_file = Some(path);
+
+    Ok(())
+  }
+
+  // Writes the RAM out in the raw layout other emulators use, followed by any battery-backed state
+  pub fn flush(&mut self) -> io::Result<()> {
+    self.unsaved_cycles = None;
+
+    let path = match self.save_file {
+      Some(ref path) => path,
+      None => return Ok(()),
+    };
+
+    let mut data = self.ram.to_vec();
+
+    data.extend(self.controller.save_battery());
+
+    fs::write(path, data)
+  }
+
+  fn load_save(&mut self, data: &[u8]) {
+    let size = self.ram.len().min(data.len());
+
+    self.ram[..size].copy_from_slice(&data[..size]);
+    self.controller.load_battery(&data[size..]);
+  }
+
+  pub fn rumble(&self) -> bool {
+    self.controller.rumble()
+  }
+}
+
+impl Drop for Cartridge {
+  fn drop(&mut self) {
+    if let Err(error) = self.flush() {
+      eprintln!("Failed to write save file: {}", error);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::env;
+  use std::process;
+
+  const MENU_START: usize = 0x18000;
+
+  // 128 KiB with an MBC1 game's header in bank 0 and an MMM01 menu in the last 32 KiB
+  fn multicart_rom() -> Vec<u8> {
+    let mut rom = test_rom(0x01, 0x02, 0x00);
+    let menu = test_rom(0x0B, 0x02, 0x00);
+
+    rom[MENU_START..].copy_from_slice(&menu[..mmm01::MENU_SIZE]);
+    rom[MENU_START + 0x0150] = 0xAA;
+
+    rom
+  }
+
+  #[test]
+  fn detects_mmm01_from_the_menu_header() {
+    let cartridge = Cartridge::new(multicart_rom().into_boxed_slice(), ClockSource::Emulated).unwrap();
+
+    assert_eq!(cartridge.header().cartridge_type.mapper, Mapper::Mmm01);
+    assert_eq!(cartridge.read_rom(0x0150), 0xAA);
+  }
+
+  #[test]
+  fn verifies_the_menu_header() {
+    let mut rom = multicart_rom();
+
+    rom[MENU_START + 0x0104] = 0x00;
+    assert_eq!(Cartridge::new(rom.into_boxed_slice(), ClockSource::Emulated).err(), Some(CartridgeError::InvalidLogo));
+
+    let mut rom = multicart_rom();
+
+    rom[MENU_START + 0x014D] ^= 0xFF;
+    match Cartridge::new(rom.into_boxed_slice(), ClockSource::Emulated) {
+      Err(CartridgeError::HeaderChecksumMismatch { .. }) => {}
+      _ => panic!("Expected a header checksum mismatch"),
+    }
+  }
+
+  #[test]
+  fn save_files_round_trip_ram_and_the_clock() {
+    let path = env::temp_dir().join(format!("rustboy-{}.sav", process::id()));
+    let _ = fs::remove_file(&path);
+
+    // MBC3 with timer, RAM and battery
+    let mut cartridge = Cartridge::new(test_rom(0x10, 0x00, 0x02).into_boxed_slice(), ClockSource::Emulated).unwrap();
+
+    cartridge.attach_save_file(&path).unwrap();
+    cartridge.write_rom(0x0000, 0x0A);
+    cartridge.write_ram(0x0123, 0x42);
+    cartridge.write_rom(0x4000, 0x08);
+    cartridge.write_ram(0x0000, 12);
+    cartridge.flush().unwrap();
+    drop(cartridge);
+
+    let data = fs::read(&path).unwrap();
+
+    assert_eq!(data.len(), RAM_BANK_SIZE + 48);
+
+    // Older files end in a 32-bit timestamp
+    for size in &[RAM_BANK_SIZE + 48, RAM_BANK_SIZE + 44] {
+      fs::write(&path, &data[..*size]).unwrap();
+
+      let mut restored = Cartridge::new(test_rom(0x10, 0x00, 0x02).into_boxed_slice(), ClockSource::Emulated).unwrap();
+
+      restored.attach_save_file(&path).unwrap();
+      restored.write_rom(0x0000, 0x0A);
+      restored.write_rom(0x6000, 0x00);
+      restored.write_rom(0x6000, 0x01);
+      restored.write_rom(0x4000, 0x08);
+      assert_eq!(restored.read_ram(0x0000), 12);
+
+      restored.write_rom(0x4000, 0x00);
+      assert_eq!(restored.read_ram(0x0123), 0x42);
+    }
+
+    fs::remove_file(&path).unwrap();
+  }
+
+  #[test]
+  fn other_cartridges_use_the_header_in_bank_0() {
+    let cartridge = Cartridge::new(test_rom(0x01, 0x02, 0x00).into_boxed_slice(), ClockSource::Emulated).unwrap();
+
+    assert_eq!(cartridge.header().cartridge_type.mapper, Mapper::Mbc1);
+  }
+}
@@ -0,0 +1,360 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use byteorder::{ByteOrder, LittleEndian};
+
+// The RTC runs off its own 32768 Hz crystal, expressed here in CPU T-cycles
+const CYCLES_PER_SECOND: u64 = 4194304;
+
+const SECONDS_PER_MINUTE: u64 = 60;
+const SECONDS_PER_HOUR: u64 = 60 * SECONDS_PER_MINUTE;
+const SECONDS_PER_DAY: u64 = 24 * SECONDS_PER_HOUR;
+const DAYS: u64 = 512; // The day counter is 9 bits wide
+
+const DAY_HIGH_MASK: u8 = 0x01;
+const HALT_FLAG: u8 = 0x40;
+const CARRY_FLAG: u8 = 0x80;
+
+const REG_SECONDS: u8 = 0x08;
+const REG_MINUTES: u8 = 0x09;
+const REG_HOURS: u8 = 0x0A;
+const REG_DAY_LOW: u8 = 0x0B;
+const REG_DAY_HIGH: u8 = 0x0C;
+
+// The block other emulators append to MBC3 save files: the running and then the
+// latched registers as 32-bit little-endian words, followed by a UNIX timestamp
+// that is 64 bits wide, or 32 bits in older files
+const SAVE_SIZE: usize = 48;
+const SAVE_SIZE_SHORT: usize = 44;
+const SAVE_TIMESTAMP: usize = 40;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClockSource {
+  Host, // Follows the host's wall clock, so time also passes while the emulator is closed
+  Emulated, // Advances with emulated cycles only, for reproducible runs
+}
+
+// Counts the whole seconds passed according to a clock source
+pub struct TimeSource {
+  source: ClockSource,
+  cycles: u64, // Emulated cycles not yet reported as elapsed
+  last_update: SystemTime, // Host time elapsed seconds were last reported up to
+}
+
+impl TimeSource {
+  pub fn new(source: ClockSource) -> TimeSource {
+    TimeSource {
+      source,
+      cycles: 0,
+      last_update: SystemTime::now(),
+    }
+  }
+
+  pub fn tick(&mut self, cycles: u32) {
+    if self.source == ClockSource::Emulated {
+      self.cycles += cycles as u64;
+    }
+  }
+
+  // Returns the seconds passed since the previous call
+  pub fn elapsed(&mut self) -> u64 {
+    match self.source {
+      ClockSource::Emulated => {
+        let seconds = self.cycles / CYCLES_PER_SECOND;
+
+        self.cycles %= CYCLES_PER_SECOND;
+
+        seconds
+      }
+
+      ClockSource::Host => {
+        let elapsed = SystemTime::now().duration_since(self.last_update).unwrap_or_else(|_| Duration::from_secs(0));
+        let seconds = elapsed.as_secs();
+
+        self.last_update += Duration::from_secs(seconds);
+
+        seconds
+      }
+    }
+  }
+
+  // Starts counting a new second from now
+  pub fn reset(&mut self) {
+    self.cycles = 0;
+    self.last_update = SystemTime::now();
+  }
+}
+
+pub struct RealTimeClock {
+  time: TimeSource,
+  seconds: u8,
+  minutes: u8,
+  hours: u8,
+  days: u16,
+  halted: bool,
+  carry: bool,
+  latched: [u8; 5],
+}
+
+impl RealTimeClock {
+  pub fn new(source: ClockSource) -> RealTimeClock {
+    RealTimeClock {
+      time: TimeSource::new(source),
+      seconds: 0,
+      minutes: 0,
+      hours: 0,
+      days: 0,
+      halted: false,
+      carry: false,
+      latched: [0; 5],
+    }
+  }
+
+  pub fn tick(&mut self, cycles: u32) {
+    self.time.tick(cycles);
+  }
+
+  // Copies the running counters into the registers visible to the CPU
+  pub fn latch(&mut self) {
+    self.update();
+
+    self.latched = [
+      self.seconds,
+      self.minutes,
+      self.hours,
+      (self.days & 0xFF) as u8,
+      self.read_day_high(),
+    ];
+  }
+
+  pub fn read_register(&self, register: u8) -> u8 {
+    self.latched[(register - REG_SECONDS) as usize]
+  }
+
+  pub fn write_register(&mut self, register: u8, value: u8) {
+    self.update();
+
+    match register {
+      REG_SECONDS => {
+        self.seconds = value & 0x3F;
+        self.time.reset();
+      }
+      REG_MINUTES => self.minutes = value & 0x3F,
+      REG_HOURS => self.hours = value & 0x1F,
+      REG_DAY_LOW => self.days = (self.days & 0x100) | value as u16,
+      REG_DAY_HIGH => {
+        self.days = (self.days & 0xFF) | ((value & DAY_HIGH_MASK) as u16) << 8;
+        self.halted = (value & HALT_FLAG) != 0;
+        self.carry = (value & CARRY_FLAG) != 0;
+      }
+      _ => unreachable!("Invalid RTC register: {:#04x}", register),
+    }
+
+    // Writes show up in the latched registers straight away
+    self.latched[(register - REG_SECONDS) as usize] = match register {
+      REG_DAY_HIGH => self.read_day_high(),
+      REG_DAY_LOW => (self.days & 0xFF) as u8,
+      REG_HOURS => self.hours,
+      REG_MINUTES => self.minutes,
+      _ => self.seconds,
+    };
+  }
+
+  pub fn save(&mut self) -> Vec<u8> {
+    self.update();
+
+    let registers = [
+      self.seconds,
+      self.minutes,
+      self.hours,
+      (self.days & 0xFF) as u8,
+      self.read_day_high(),
+    ];
+
+    let mut data = vec![0; SAVE_SIZE];
+
+    for (index, &value) in registers.iter().chain(self.latched.iter()).enumerate() {
+      LittleEndian::write_u32(&mut data[index * 4..], value as u32);
+    }
+
+    LittleEndian::write_u64(&mut data[SAVE_TIMESTAMP..], unix_time());
+
+    data
+  }
+
+  // Restores a saved clock, catching up on the time the emulator was closed
+  // when following the host's clock
+  pub fn load(&mut self, data: &[u8]) {
+    if data.len() < SAVE_SIZE_SHORT {
+      return;
+    }
+
+    let register = |index: usize| LittleEndian::read_u32(&data[index * 4..]) as u8;
+    let day_high = register(4);
+
+    self.seconds = register(0) & 0x3F;
+    self.minutes = register(1) & 0x3F;
+    self.hours = register(2) & 0x1F;
+    self.days = register(3) as u16 | ((day_high & DAY_HIGH_MASK) as u16) << 8;
+    self.halted = (day_high & HALT_FLAG) != 0;
+    self.carry = (day_high & CARRY_FLAG) != 0;
+
+    for (index, latched) in self.latched.iter_mut().enumerate() {
+      *latched = register(5 + index);
+    }
+
+    let timestamp = if data.len() >= SAVE_SIZE {
+      LittleEndian::read_u64(&data[SAVE_TIMESTAMP..])
+    } else {
+      LittleEndian::read_u32(&data[SAVE_TIMESTAMP..]) as u64
+    };
+
+    self.time.reset();
+
+    if self.time.source == ClockSource::Host && !self.halted {
+      self.advance(unix_time().saturating_sub(timestamp));
+    }
+  }
+
+  fn read_day_high(&self) -> u8 {
+    let mut value = ((self.days >> 8) as u8) & DAY_HIGH_MASK;
+
+    if self.halted { value |= HALT_FLAG; }
+    if self.carry { value |= CARRY_FLAG; }
+
+    value
+  }
+
+  // Brings the counters up to date with the clock source
+  fn update(&mut self) {
+    let seconds = self.time.elapsed();
+
+    if !self.halted {
+      self.advance(seconds);
+    }
+  }
+
+  fn advance(&mut self, seconds: u64) {
+    if seconds == 0 {
+      return;
+    }
+
+    let total = self.seconds as u64 +
+      self.minutes as u64 * SECONDS_PER_MINUTE +
+      self.hours as u64 * SECONDS_PER_HOUR +
+      self.days as u64 * SECONDS_PER_DAY +
+      seconds;
+
+    let days = total / SECONDS_PER_DAY;
+
+    self.seconds = (total % SECONDS_PER_MINUTE) as u8;
+    self.minutes = ((total / SECONDS_PER_MINUTE) % 60) as u8;
+    self.hours = ((total / SECONDS_PER_HOUR) % 24) as u8;
+    self.days = (days % DAYS) as u16;
+
+    // The carry bit stays set until the game clears it
+    if days >= DAYS {
+      self.carry = true;
+    }
+  }
+}
+
+fn unix_time() -> u64 {
+  SystemTime::now().duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn latched(clock: &mut RealTimeClock) -> [u8; 5] {
+    clock.latch();
+
+    [
+      clock.read_register(REG_SECONDS),
+      clock.read_register(REG_MINUTES),
+      clock.read_register(REG_HOURS),
+      clock.read_register(REG_DAY_LOW),
+      clock.read_register(REG_DAY_HIGH),
+    ]
+  }
+
+  #[test]
+  fn counters_roll_over_into_days_and_the_carry() {
+    let mut clock = RealTimeClock::new(ClockSource::Emulated);
+
+    clock.write_register(REG_SECONDS, 59);
+    clock.write_register(REG_MINUTES, 59);
+    clock.write_register(REG_HOURS, 23);
+    clock.write_register(REG_DAY_LOW, 0x00);
+    clock.tick(CYCLES_PER_SECOND as u32);
+
+    assert_eq!(latched(&mut clock), [0, 0, 0, 0x01, 0x00]);
+
+    clock.write_register(REG_SECONDS, 59);
+    clock.write_register(REG_MINUTES, 59);
+    clock.write_register(REG_HOURS, 23);
+    clock.write_register(REG_DAY_LOW, 0xFF);
+    clock.write_register(REG_DAY_HIGH, 0x01);
+    clock.tick(CYCLES_PER_SECOND as u32);
+
+    assert_eq!(latched(&mut clock), [0, 0, 0, 0x00, CARRY_FLAG]);
+
+    // The carry stays set as time goes on
+    clock.tick(CYCLES_PER_SECOND as u32);
+
+    assert_eq!(latched(&mut clock), [1, 0, 0, 0x00, CARRY_FLAG]);
+  }
+
+  #[test]
+  fn halt_flag_freezes_time() {
+    let mut clock = RealTimeClock::new(ClockSource::Emulated);
+
+    clock.write_register(REG_DAY_HIGH, HALT_FLAG);
+    clock.tick(5 * CYCLES_PER_SECOND as u32);
+
+    assert_eq!(latched(&mut clock), [0, 0, 0, 0, HALT_FLAG]);
+
+    clock.write_register(REG_DAY_HIGH, 0x00);
+    clock.tick(CYCLES_PER_SECOND as u32);
+
+    assert_eq!(latched(&mut clock), [1, 0, 0, 0, 0]);
+  }
+
+  #[test]
+  fn writing_seconds_restarts_the_current_second() {
+    let mut clock = RealTimeClock::new(ClockSource::Emulated);
+
+    clock.tick(CYCLES_PER_SECOND as u32 - 4);
+    clock.write_register(REG_SECONDS, 10);
+    clock.tick(4);
+
+    assert_eq!(latched(&mut clock)[0], 10);
+
+    clock.tick(CYCLES_PER_SECOND as u32 - 4);
+
+    assert_eq!(latched(&mut clock)[0], 11);
+  }
+
+  #[test]
+  fn save_round_trips_both_block_sizes() {
+    let mut clock = RealTimeClock::new(ClockSource::Emulated);
+
+    clock.write_register(REG_SECONDS, 12);
+    clock.write_register(REG_MINUTES, 34);
+    clock.write_register(REG_HOURS, 5);
+    clock.write_register(REG_DAY_LOW, 0x67);
+    clock.write_register(REG_DAY_HIGH, 0x01);
+
+    let data = clock.save();
+
+    assert_eq!(data.len(), SAVE_SIZE);
+
+    for size in &[SAVE_SIZE, SAVE_SIZE_SHORT] {
+      let mut restored = RealTimeClock::new(ClockSource::Emulated);
+
+      restored.load(&data[..*size]);
+
+      assert_eq!(latched(&mut restored), [12, 34, 5, 0x67, 0x01]);
+    }
+  }
+}
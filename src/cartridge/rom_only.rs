@@ -0,0 +1,25 @@
+use super::{MemoryBankController, read_rom_bank, ram_offset};
+
+// Plain 32 KiB cartridges, optionally with up to 8 KiB of RAM
+pub struct RomOnly;
+
+impl MemoryBankController for RomOnly {
+  fn read_rom(&self, rom: &[u8], address: u16) -> u8 {
+    read_rom_bank(rom, (address >> 14) as usize, address)
+  }
+
+  fn write_register(&mut self, _address: u16, _value: u8) {}
+
+  fn read_ram(&self, ram: &[u8], address: u16) -> u8 {
+    match ram_offset(ram, 0, address) {
+      Some(offset) => ram[offset],
+      None => 0xFF,
+    }
+  }
+
+  fn write_ram(&mut self, ram: &mut [u8], address: u16, value: u8) {
+    if let Some(offset) = ram_offset(ram, 0, address) {
+      ram[offset] = value;
+    }
+  }
+}
@@ -0,0 +1,413 @@
+use std::collections::VecDeque;
+
+use super::super::memory::Memory;
+
+use super::{Display, SCREEN_WIDTH, BACKGROUND_ENABLE, BACKGROUND_MAP, WINDOW_ENABLE, WINDOW_MAP,
+            OBJECT_ENABLE, TILE_MAP_0, TILE_MAP_1};
+use super::scanline::{apply_palette, WINDOW_X_OFFSET, WINDOW_X_MAX};
+use super::sprite::{Sprite, SPRITE_X_OFFSET};
+
+// Each fetcher step takes two dots: tile number, low bitplane, high bitplane
+const FETCH_DOTS: u8 = 6;
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+// The first tile of every line is fetched twice, the first result being thrown away
+const STARTUP_DOTS: u8 = 6;
+
+#[derive(Clone, Copy)]
+struct SpritePixel {
+    color: u8,
+    palette: usize,
+    behind_background: bool,
+}
+
+// Fetches a row of eight pixels at a time from the background or window into the FIFO
+struct Fetcher {
+    dot: u8,
+    tile_x: u8,
+    window: bool,
+    tile: u8,
+    low: u8,
+    high: u8,
+}
+
+impl Fetcher {
+    fn new(window: bool) -> Fetcher {
+        Fetcher {
+            dot: 0,
+            tile_x: 0,
+            window,
+            tile: 0,
+            low: 0,
+            high: 0,
+        }
+    }
+}
+
+// The state of pixel transfer (mode 3) when pixels are pushed out one dot at a time, so
+// its length varies with the fine scroll, the window and sprites, and register writes
+// made part way through a line take effect from the next pixel or tile
+pub struct PixelFifo {
+    background: VecDeque<u8>,
+    sprites: VecDeque<SpritePixel>,
+    fetcher: Fetcher,
+    x: u8, // The next pixel on the line to be output
+    discard: u8, // Pixels of the first tile scrolled off the left edge
+    delay: u8, // Dots the output is stalled for
+    sprite_fetch: Option<usize>, // A sprite waiting for the fetcher, as an index into the line's sprites
+    fetched: Vec<bool>,
+    window_drawn: bool,
+}
+
+impl PixelFifo {
+    pub fn new() -> PixelFifo {
+        PixelFifo {
+            background: VecDeque::with_capacity(8),
+            sprites: VecDeque::with_capacity(8),
+            fetcher: Fetcher::new(false),
+            x: 0,
+            discard: 0,
+            delay: 0,
+            sprite_fetch: None,
+            fetched: Vec::new(),
+            window_drawn: false,
+        }
+    }
+}
+
+impl Display {
+    pub(super) fn start_transfer(&mut self) {
+        self.fifo.background.clear();
+        self.fifo.sprites.clear();
+        self.fifo.fetcher = Fetcher::new(false);
+        self.fifo.x = 0;
+        self.fifo.discard = self.scroll_x % 8;
+        self.fifo.delay = STARTUP_DOTS;
+        self.fifo.sprite_fetch = None;
+        self.fifo.fetched = vec![false; self.sprites.len()];
+        self.fifo.window_drawn = false;
+    }
+
+    // Runs pixel transfer for one dot, returning whether the line is complete
+    pub(super) fn step_transfer(&mut self) -> bool {
+        if self.fifo.delay > 0 {
+            self.fifo.delay -= 1;
+
+            if self.fifo.delay == 0 {
+                if let Some(index) = self.fifo.sprite_fetch.take() {
+                    self.load_sprite(index);
+                }
+            }
+
+            return false;
+        }
+
+        if self.fifo.sprite_fetch.is_none() {
+            self.fifo.sprite_fetch = self.next_sprite();
+        }
+
+        if self.fifo.sprite_fetch.is_some() {
+            // The sprite fetch waits for the background fetcher to finish the tile it's on
+            if self.fifo.background.is_empty() || self.fifo.fetcher.dot < FETCH_DOTS - 1 {
+                self.step_fetcher();
+            } else {
+                self.fifo.delay = SPRITE_FETCH_DOTS - 1; // Counting this dot
+            }
+
+            return false;
+        }
+
+        self.start_window();
+
+        if !self.fifo.background.is_empty() {
+            self.output_pixel();
+        }
+
+        self.step_fetcher();
+
+        self.fifo.x as usize == SCREEN_WIDTH
+    }
+
+    pub(super) fn end_transfer(&mut self) {
+        if self.fifo.window_drawn {
+            self.window_line += 1;
+        }
+    }
+
+    fn next_sprite(&self) -> Option<usize> {
+        if self.lcd_control & OBJECT_ENABLE == 0 || self.fifo.discard > 0 {
+            return None;
+        }
+
+        let x = self.fifo.x + SPRITE_X_OFFSET;
+
+        self.sprites.iter()
+            .enumerate()
+            .position(|(index, sprite)| !self.fifo.fetched[index] && sprite.x <= x)
+    }
+
+    fn start_window(&mut self) {
+        let window = self.lcd_control & WINDOW_ENABLE != 0 &&
+            self.lcd_control & BACKGROUND_ENABLE != 0 &&
+            self.line >= self.window_y &&
+            self.window_x <= WINDOW_X_MAX &&
+            self.fifo.x + WINDOW_X_OFFSET >= self.window_x;
+
+        if window && !self.fifo.fetcher.window {
+            self.fifo.background.clear();
+            self.fifo.fetcher = Fetcher::new(true);
+            self.fifo.window_drawn = true;
+        }
+    }
+
+    fn step_fetcher(&mut self) {
+        let fetcher = &self.fifo.fetcher;
+
+        match fetcher.dot {
+            1 => {
+                let tile = if fetcher.window {
+                    let map = if self.lcd_control & WINDOW_MAP != 0 { TILE_MAP_1 } else { TILE_MAP_0 };
+
+                    self.read_tile_map(map, fetcher.tile_x * 8, self.window_line)
+                } else {
+                    let map = if self.lcd_control & BACKGROUND_MAP != 0 { TILE_MAP_1 } else { TILE_MAP_0 };
+                    let x = (self.scroll_x & !0x07).wrapping_add(fetcher.tile_x * 8);
+
+                    self.read_tile_map(map, x, self.scroll_y.wrapping_add(self.line))
+                };
+
+                self.fifo.fetcher.tile = tile;
+            }
+            3 | 5 => {
+                let y = if fetcher.window { self.window_line } else { self.scroll_y.wrapping_add(self.line) } % 8;
+                let address = self.background_tile_address(fetcher.tile) + y as u16 * 2;
+
+                if fetcher.dot == 3 {
+                    self.fifo.fetcher.low = self.vram.read_byte(address);
+                } else {
+                    self.fifo.fetcher.high = self.vram.read_byte(address + 1);
+                }
+            }
+            _ => {}
+        }
+
+        if self.fifo.fetcher.dot < FETCH_DOTS {
+            self.fifo.fetcher.dot += 1;
+        }
+
+        if self.fifo.fetcher.dot == FETCH_DOTS && self.fifo.background.is_empty() {
+            let (low, high) = (self.fifo.fetcher.low, self.fifo.fetcher.high);
+
+            for bit in (0..8).rev() {
+                self.fifo.background.push_back(((high >> bit) & 0x01) << 1 | ((low >> bit) & 0x01));
+            }
+
+            self.fifo.fetcher.dot = 0;
+            self.fifo.fetcher.tile_x = (self.fifo.fetcher.tile_x + 1) % 32;
+        }
+    }
+
+    fn output_pixel(&mut self) {
+        let background = self.fifo.background.pop_front().unwrap_or(0);
+        let sprite = self.fifo.sprites.pop_front();
+
+        if self.fifo.discard > 0 && !self.fifo.fetcher.window {
+            self.fifo.discard -= 1;
+            return;
+        }
+
+        let background = if self.lcd_control & BACKGROUND_ENABLE != 0 { background } else { 0 };
+        let mut shade = apply_palette(self.background_palette, background);
+
+        if let Some(sprite) = sprite {
+            if sprite.color != 0 && self.lcd_control & OBJECT_ENABLE != 0 &&
+                !(sprite.behind_background && background != 0) {
+                shade = apply_palette(self.object_palettes[sprite.palette], sprite.color);
+            }
+        }
+
+        self.framebuffer[self.line as usize * SCREEN_WIDTH + self.fifo.x as usize] = shade;
+        self.fifo.x += 1;
+    }
+
+    // Mixes a sprite's row into the sprite FIFO, where pixels from sprites
+    // loaded earlier keep priority unless they are transparent
+    fn load_sprite(&mut self, index: usize) {
+        let sprite: Sprite = self.sprites[index];
+        let (address, y) = match self.sprite_row(&sprite) {
+            Some(row) => row,
+            None => {
+                self.fifo.fetched[index] = true;
+                return;
+            }
+        };
+
+        // Sprites partly off the left edge only contribute their visible pixels
+        let skip = (self.fifo.x + SPRITE_X_OFFSET).saturating_sub(sprite.x);
+
+        for x in skip..8 {
+            let pixel = SpritePixel {
+                color: self.read_tile_pixel(address, if sprite.flip_x() { 7 - x } else { x }, y),
+                palette: sprite.palette(),
+                behind_background: sprite.behind_background(),
+            };
+
+            let slot = (x - skip) as usize;
+
+            if slot >= self.fifo.sprites.len() {
+                self.fifo.sprites.push_back(pixel);
+            } else if self.fifo.sprites[slot].color == 0 {
+                self.fifo.sprites[slot] = pixel;
+            }
+        }
+
+        self.fifo.fetched[index] = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Renderer, Mode, REG_LCD_CONTROL, REG_LCD_STATUS, REG_SCROLL_X, REG_WINDOW_X, REG_WINDOW_Y,
+                       REG_BACKGROUND_PALETTE, LCD_ENABLE, OBJECT_ENABLE, WINDOW_ENABLE, TILE_DATA, TILE_SIZE};
+    use super::super::super::interrupts::Interrupts;
+
+    // Turns the LCD on, then counts the dots from the start of pixel transfer on the first
+    // line until STAT switches to HBlank
+    fn transfer_dots(display: &mut Display, lcd_control: u8) -> u32 {
+        let mut interrupts = Interrupts::default();
+        let mut dots = 0;
+
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE | lcd_control);
+
+        while display.read_register(REG_LCD_STATUS) & 0x03 != 3 {
+            display.tick(1, &mut interrupts);
+        }
+
+        while display.read_register(REG_LCD_STATUS) & 0x03 == 3 {
+            display.tick(1, &mut interrupts);
+            dots += 1;
+        }
+
+        dots
+    }
+
+    // Tick until the given pixel is next to be output
+    fn run_to_pixel(display: &mut Display, x: u8) {
+        let mut interrupts = Interrupts::default();
+
+        while display.mode != Mode::Transfer || display.fifo.x < x {
+            display.tick(1, &mut interrupts);
+        }
+    }
+
+    fn finish_line(display: &mut Display) {
+        let mut interrupts = Interrupts::default();
+
+        while display.mode == Mode::Transfer {
+            display.tick(1, &mut interrupts);
+        }
+    }
+
+    #[test]
+    fn fine_scroll_lengthens_transfer() {
+        for &(scroll_x, dots) in &[(0, 172), (3, 175), (7, 179), (8, 172)] {
+            let mut display = Display::new(false, Renderer::Fifo);
+
+            display.write_register(REG_SCROLL_X, scroll_x);
+            assert_eq!(transfer_dots(&mut display, BACKGROUND_ENABLE), dots, "SCX {}", scroll_x);
+        }
+    }
+
+    #[test]
+    fn sprites_stall_the_fetcher() {
+        // Six dots for the fetch, plus up to five more waiting for the background fetcher
+        for &(x, dots) in &[(0, 183), (8, 183), (12, 179), (15, 178), (167, 178), (168, 172)] {
+            let mut display = Display::new(false, Renderer::Fifo);
+
+            display.write_oam(0, 16);
+            display.write_oam(1, x);
+            assert_eq!(transfer_dots(&mut display, BACKGROUND_ENABLE | OBJECT_ENABLE), dots, "X {}", x);
+        }
+
+        // A second sprite on the same tile only costs the fetch itself
+        let mut display = Display::new(false, Renderer::Fifo);
+
+        for index in 0..2 {
+            display.write_oam(index * 4, 16);
+            display.write_oam(index * 4 + 1, 8);
+        }
+
+        assert_eq!(transfer_dots(&mut display, BACKGROUND_ENABLE | OBJECT_ENABLE), 189);
+
+        // Nothing is fetched with sprites turned off
+        let mut display = Display::new(false, Renderer::Fifo);
+
+        display.write_oam(0, 16);
+        display.write_oam(1, 8);
+        assert_eq!(transfer_dots(&mut display, BACKGROUND_ENABLE), 172);
+    }
+
+    #[test]
+    fn the_window_restarts_the_fetcher() {
+        let mut display = Display::new(false, Renderer::Fifo);
+
+        display.write_register(REG_WINDOW_X, 50);
+        display.write_register(REG_WINDOW_Y, 0);
+        assert_eq!(transfer_dots(&mut display, BACKGROUND_ENABLE | WINDOW_ENABLE), 178);
+        assert!(display.fifo.window_drawn);
+
+        // Past the right edge it never starts
+        let mut display = Display::new(false, Renderer::Fifo);
+
+        display.write_register(REG_WINDOW_X, WINDOW_X_MAX + 1);
+        assert_eq!(transfer_dots(&mut display, BACKGROUND_ENABLE | WINDOW_ENABLE), 172);
+    }
+
+    #[test]
+    fn palette_writes_apply_from_the_next_pixel() {
+        let mut display = Display::new(false, Renderer::Fifo);
+
+        // Tile 0 is solid colour 3, and fills the whole map
+        for offset in 0..TILE_SIZE {
+            display.write_vram(offset, 0xFF);
+        }
+
+        display.write_register(REG_BACKGROUND_PALETTE, 0xFF);
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE | TILE_DATA | BACKGROUND_ENABLE);
+
+        run_to_pixel(&mut display, 37);
+        display.write_register(REG_BACKGROUND_PALETTE, 0x3F);
+        finish_line(&mut display);
+
+        assert!(display.framebuffer[..37].iter().all(|&shade| shade == 3));
+        assert!(display.framebuffer[37..SCREEN_WIDTH].iter().all(|&shade| shade == 0));
+    }
+
+    #[test]
+    fn scroll_writes_apply_from_the_next_tile() {
+        let mut display = Display::new(false, Renderer::Fifo);
+
+        // Colour 0 in the left half of the map and colour 3 in the right
+        for offset in TILE_SIZE..TILE_SIZE * 2 {
+            display.write_vram(offset, 0xFF);
+        }
+
+        for column in 16..32 {
+            display.write_vram(TILE_MAP_0 + column, 0x01);
+        }
+
+        display.write_register(REG_BACKGROUND_PALETTE, 0xE4);
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE | TILE_DATA | BACKGROUND_ENABLE);
+
+        run_to_pixel(&mut display, 40);
+        display.write_register(REG_SCROLL_X, 128);
+        finish_line(&mut display);
+
+        // Whatever was already fetched still goes out unchanged, then the fetcher
+        // carries on from the right half of the map and wraps back to its start
+        assert!(display.framebuffer[..48].iter().all(|&shade| shade == 0));
+        assert!(display.framebuffer[48..128].iter().all(|&shade| shade == 3));
+        assert!(display.framebuffer[128..SCREEN_WIDTH].iter().all(|&shade| shade == 0));
+    }
+}
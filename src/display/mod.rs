@@ -1,15 +1,540 @@
-use super::memory::{Memory};
+mod sprite;
+mod scanline;
+mod fifo;
 
-#[allow(dead_code)]
-pub struct Display<M: Memory> {
-    memory: M,
+use super::memory::{Memory, RandomAccessMemory};
+use super::interrupts::{Interrupt, Interrupts};
+
+use self::sprite::Sprite;
+use self::fifo::PixelFifo;
+
+pub const VRAM_SIZE: u16 = 0x2000;
+pub const OAM_SIZE: u16 = 0xA0; // 40 sprites of 4 bytes each
+
+pub const SCREEN_WIDTH: usize = 160;
+pub const SCREEN_HEIGHT: usize = 144;
+
+// Every line takes 456 dots (T-cycles at normal speed), split between the modes
+const DOTS_PER_LINE: u16 = 456;
+const OAM_SCAN_DOTS: u16 = 80;
+const TRANSFER_DOTS: u16 = 172;
+const LINES_PER_FRAME: u8 = 154; // The last ten are VBlank
+
+const REG_LCD_CONTROL: u16 = 0xFF40; // LCDC
+const REG_LCD_STATUS: u16 = 0xFF41; // STAT
+const REG_SCROLL_Y: u16 = 0xFF42; // SCY
+const REG_SCROLL_X: u16 = 0xFF43; // SCX
+const REG_LINE: u16 = 0xFF44; // LY
+const REG_LINE_COMPARE: u16 = 0xFF45; // LYC
+const REG_BACKGROUND_PALETTE: u16 = 0xFF47; // BGP
+const REG_OBJECT_PALETTE_0: u16 = 0xFF48; // OBP0
+const REG_OBJECT_PALETTE_1: u16 = 0xFF49; // OBP1
+const REG_WINDOW_Y: u16 = 0xFF4A; // WY
+const REG_WINDOW_X: u16 = 0xFF4B; // WX
+
+// CGB only
+const REG_VRAM_BANK: u16 = 0xFF4F; // VBK
+const REG_BACKGROUND_PALETTE_INDEX: u16 = 0xFF68; // BCPS
+const REG_BACKGROUND_PALETTE_DATA: u16 = 0xFF69; // BCPD
+const REG_OBJECT_PALETTE_INDEX: u16 = 0xFF6A; // OCPS
+const REG_OBJECT_PALETTE_DATA: u16 = 0xFF6B; // OCPD
+const REG_OBJECT_PRIORITY: u16 = 0xFF6C; // OPRI
+
+const LCD_ENABLE: u8 = 0x80;
+const WINDOW_MAP: u8 = 0x40;
+const WINDOW_ENABLE: u8 = 0x20;
+const TILE_DATA: u8 = 0x10; // Unsigned tile indices from 0x8000 rather than signed ones around 0x9000
+const BACKGROUND_MAP: u8 = 0x08;
+const OBJECT_SIZE: u8 = 0x04; // 8x16 rather than 8x8 sprites
+const OBJECT_ENABLE: u8 = 0x02;
+const BACKGROUND_ENABLE: u8 = 0x01; // Also disables the window on the DMG
+
+const TILE_MAP_0: u16 = 0x1800;
+const TILE_MAP_1: u16 = 0x1C00;
+const TILE_DATA_0: u16 = 0x0000;
+const TILE_DATA_1: u16 = 0x1000; // Tile 0 when indices are signed
+const TILE_SIZE: u16 = 16; // 8 rows of two bitplanes
+
+const STATUS_UNUSED: u8 = 0x80;
+const STATUS_INTERRUPT_MASK: u8 = 0x78; // Only the interrupt selects are writable
+const COINCIDENCE_FLAG: u8 = 0x04;
+const HBLANK_SELECT: u8 = 0x08;
+const VBLANK_SELECT: u8 = 0x10;
+const OAM_SCAN_SELECT: u8 = 0x20;
+const COINCIDENCE_SELECT: u8 = 0x40;
+
+const PALETTE_MEMORY_SIZE: usize = 0x40; // 8 palettes of 4 colours, 2 bytes each
+const PALETTE_INDEX_MASK: u8 = 0x3F;
+const PALETTE_AUTO_INCREMENT: u8 = 0x80;
+
+// How pixel transfer is emulated
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Renderer {
+    Scanline, // Draws each line at once with a fixed mode 3 length, which is fast
+    Fifo, // Pushes out a pixel per dot, for effects that change registers mid-line
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Mode {
+    HBlank,
+    VBlank,
+    OamScan,
+    Transfer,
+}
+
+// A CGB palette register pair: an index into palette memory and the data port that accesses it
+struct ColorPalettes {
+    index: u8,
+    data: [u8; PALETTE_MEMORY_SIZE],
 }
 
-#[allow(dead_code)]
-impl<M: Memory> Display<M> {
-    pub fn new(memory: M) -> Self {
+impl ColorPalettes {
+    fn new() -> Self {
+        ColorPalettes {
+            index: 0,
+            data: [0xFF; PALETTE_MEMORY_SIZE],
+        }
+    }
+
+    fn read_index(&self) -> u8 {
+        self.index | 0x40
+    }
+
+    fn write_index(&mut self, value: u8) {
+        self.index = value & (PALETTE_AUTO_INCREMENT | PALETTE_INDEX_MASK);
+    }
+
+    fn read_data(&self) -> u8 {
+        self.data[(self.index & PALETTE_INDEX_MASK) as usize]
+    }
+
+    fn write_data(&mut self, value: u8) {
+        self.data[(self.index & PALETTE_INDEX_MASK) as usize] = value;
+
+        if self.index & PALETTE_AUTO_INCREMENT != 0 {
+            self.index = PALETTE_AUTO_INCREMENT | (self.index.wrapping_add(1) & PALETTE_INDEX_MASK);
+        }
+    }
+}
+
+// The video hardware, which owns the tile data, maps and sprite attributes it draws from
+pub struct Display {
+    color: bool,
+    renderer: Renderer,
+    vram: RandomAccessMemory,
+    vram_bank: u8,
+    oam: RandomAccessMemory,
+
+    mode: Mode,
+    dot: u16, // Position within the current line
+    sprites: Vec<Sprite>, // Found on the current line by the OAM scan
+    fifo: PixelFifo,
+    window_line: u8, // Only advances on lines the window was drawn on
+    framebuffer: Box<[u8]>, // Shades from 0 (white) to 3 (black)
+    frame_ready: bool,
+    stat_line: bool, // The selected STAT sources OR'd together, which interrupts on a rising edge
+
+    lcd_control: u8,
+    status: u8, // Interrupt selects only, the rest is derived when read
+    scroll_y: u8,
+    scroll_x: u8,
+    line: u8,
+    line_compare: u8,
+    background_palette: u8,
+    object_palettes: [u8; 2],
+    window_y: u8,
+    window_x: u8,
+
+    background_colors: ColorPalettes,
+    object_colors: ColorPalettes,
+    object_priority: u8,
+}
+
+impl Display {
+    pub fn new(color: bool, renderer: Renderer) -> Self {
+        // The CGB has a second bank of VRAM for tile data and map attributes
+        let vram_size = if color { VRAM_SIZE as usize * 2 } else { VRAM_SIZE as usize };
+
         Display {
-            memory: memory,
+            color,
+            renderer,
+            vram: RandomAccessMemory::new(vram_size),
+            vram_bank: 0,
+            oam: RandomAccessMemory::new(OAM_SIZE as usize),
+
+            mode: Mode::HBlank,
+            dot: 0,
+            sprites: Vec::new(),
+            fifo: PixelFifo::new(),
+            window_line: 0,
+            framebuffer: vec![0; SCREEN_WIDTH * SCREEN_HEIGHT].into_boxed_slice(),
+            frame_ready: false,
+            stat_line: false,
+
+            lcd_control: 0,
+            status: 0,
+            scroll_y: 0,
+            scroll_x: 0,
+            line: 0,
+            line_compare: 0,
+            background_palette: 0,
+            object_palettes: [0; 2],
+            window_y: 0,
+            window_x: 0,
+
+            background_colors: ColorPalettes::new(),
+            object_colors: ColorPalettes::new(),
+            object_priority: 0,
+        }
+    }
+
+    // Advances the PPU by the given number of dots
+    pub fn tick(&mut self, cycles: u32, interrupts: &mut Interrupts) {
+        if self.lcd_control & LCD_ENABLE == 0 {
+            return;
+        }
+
+        for _ in 0..cycles {
+            self.step(interrupts);
+            self.update_stat_line(interrupts);
+        }
+    }
+
+    // VRAM is in use by the PPU during pixel transfer
+    pub fn vram_accessible(&self) -> bool {
+        self.mode != Mode::Transfer
+    }
+
+    // OAM is in use by the PPU during the OAM scan and pixel transfer
+    pub fn oam_accessible(&self) -> bool {
+        self.mode != Mode::OamScan && self.mode != Mode::Transfer
+    }
+
+    // Returns the last completed frame, once per frame
+    pub fn take_frame(&mut self) -> Option<&[u8]> {
+        if self.frame_ready {
+            self.frame_ready = false;
+
+            Some(&self.framebuffer)
+        } else {
+            None
+        }
+    }
+
+    pub fn read_vram(&self, offset: u16) -> u8 {
+        self.vram.read_byte(self.vram_offset(offset))
+    }
+
+    pub fn write_vram(&mut self, offset: u16, value: u8) {
+        let offset = self.vram_offset(offset);
+
+        self.vram.write_byte(offset, value);
+    }
+
+    pub fn read_oam(&self, offset: u16) -> u8 {
+        self.oam.read_byte(offset)
+    }
+
+    pub fn write_oam(&mut self, offset: u16, value: u8) {
+        self.oam.write_byte(offset, value);
+    }
+
+    pub fn read_register(&self, register: u16) -> u8 {
+        match register {
+            REG_LCD_CONTROL => self.lcd_control,
+            REG_LCD_STATUS => {
+                let coincidence = if self.line == self.line_compare { COINCIDENCE_FLAG } else { 0 };
+
+                STATUS_UNUSED | self.status | coincidence | self.mode as u8
+            }
+            REG_SCROLL_Y => self.scroll_y,
+            REG_SCROLL_X => self.scroll_x,
+            REG_LINE => self.line,
+            REG_LINE_COMPARE => self.line_compare,
+            REG_BACKGROUND_PALETTE => self.background_palette,
+            REG_OBJECT_PALETTE_0 => self.object_palettes[0],
+            REG_OBJECT_PALETTE_1 => self.object_palettes[1],
+            REG_WINDOW_Y => self.window_y,
+            REG_WINDOW_X => self.window_x,
+
+            _ if !self.color => 0xFF,
+
+            REG_VRAM_BANK => 0xFE | self.vram_bank,
+            REG_BACKGROUND_PALETTE_INDEX => self.background_colors.read_index(),
+            REG_BACKGROUND_PALETTE_DATA => self.background_colors.read_data(),
+            REG_OBJECT_PALETTE_INDEX => self.object_colors.read_index(),
+            REG_OBJECT_PALETTE_DATA => self.object_colors.read_data(),
+            REG_OBJECT_PRIORITY => 0xFE | self.object_priority,
+            _ => unreachable!("Invalid display register: {:#06x}", register),
+        }
+    }
+
+    pub fn write_register(&mut self, register: u16, value: u8) {
+        match register {
+            REG_LCD_CONTROL => self.write_lcd_control(value),
+            REG_LCD_STATUS => self.status = value & STATUS_INTERRUPT_MASK,
+            REG_SCROLL_Y => self.scroll_y = value,
+            REG_SCROLL_X => self.scroll_x = value,
+            REG_LINE => {} // LY is read-only
+            REG_LINE_COMPARE => self.line_compare = value,
+            REG_BACKGROUND_PALETTE => self.background_palette = value,
+            REG_OBJECT_PALETTE_0 => self.object_palettes[0] = value,
+            REG_OBJECT_PALETTE_1 => self.object_palettes[1] = value,
+            REG_WINDOW_Y => self.window_y = value,
+            REG_WINDOW_X => self.window_x = value,
+
+            _ if !self.color => {}
+
+            REG_VRAM_BANK => self.vram_bank = value & 0x01,
+            REG_BACKGROUND_PALETTE_INDEX => self.background_colors.write_index(value),
+            REG_BACKGROUND_PALETTE_DATA => self.background_colors.write_data(value),
+            REG_OBJECT_PALETTE_INDEX => self.object_colors.write_index(value),
+            REG_OBJECT_PALETTE_DATA => self.object_colors.write_data(value),
+            REG_OBJECT_PRIORITY => self.object_priority = value & 0x01,
+            _ => unreachable!("Invalid display register: {:#06x}", register),
+        }
+    }
+
+    fn vram_offset(&self, offset: u16) -> u16 {
+        self.vram_bank as u16 * VRAM_SIZE + offset
+    }
+
+    // Turning the LCD off resets the PPU to the start of the first line,
+    // where it picks up again once turned back on
+    fn write_lcd_control(&mut self, value: u8) {
+        let enabled = self.lcd_control & LCD_ENABLE != 0;
+
+        self.lcd_control = value;
+
+        if enabled && value & LCD_ENABLE == 0 {
+            self.line = 0;
+            self.dot = 0;
+            self.mode = Mode::HBlank;
+            self.stat_line = false;
+        } else if !enabled && value & LCD_ENABLE != 0 {
+            self.start_line();
+        }
+    }
+
+    fn step(&mut self, interrupts: &mut Interrupts) {
+        self.dot += 1;
+
+        if self.mode == Mode::OamScan && self.dot == OAM_SCAN_DOTS {
+            self.mode = Mode::Transfer;
+
+            if self.renderer == Renderer::Fifo {
+                self.start_transfer();
+            }
+        } else if self.mode == Mode::Transfer {
+            let done = match self.renderer {
+                Renderer::Scanline => self.dot == OAM_SCAN_DOTS + TRANSFER_DOTS,
+                Renderer::Fifo => self.step_transfer(),
+            };
+
+            if done {
+                match self.renderer {
+                    Renderer::Scanline => self.render_line(),
+                    Renderer::Fifo => self.end_transfer(),
+                }
+
+                self.mode = Mode::HBlank;
+            }
+        }
+
+        if self.dot == DOTS_PER_LINE {
+            self.dot = 0;
+            self.line = (self.line + 1) % LINES_PER_FRAME;
+            self.start_line();
+
+            if self.line as usize == SCREEN_HEIGHT {
+                interrupts.request(Interrupt::VBlank);
+            }
+        }
+    }
+
+    // STAT blocking: while any selected source holds the line high, others
+    // becoming active don't raise another interrupt
+    fn update_stat_line(&mut self, interrupts: &mut Interrupts) {
+        let source = match self.mode {
+            Mode::HBlank => HBLANK_SELECT,
+            Mode::VBlank => VBLANK_SELECT,
+            Mode::OamScan => OAM_SCAN_SELECT,
+            Mode::Transfer => 0,
+        };
+
+        let coincidence = self.line == self.line_compare && self.status & COINCIDENCE_SELECT != 0;
+        let stat_line = self.status & source != 0 || coincidence;
+
+        if stat_line && !self.stat_line {
+            interrupts.request(Interrupt::LcdStat);
+        }
+
+        self.stat_line = stat_line;
+    }
+
+    fn start_line(&mut self) {
+        if self.line as usize == SCREEN_HEIGHT {
+            self.mode = Mode::VBlank;
+            self.frame_ready = true;
+        } else if (self.line as usize) < SCREEN_HEIGHT {
+            if self.line == 0 {
+                self.window_line = 0;
+            }
+
+            self.mode = Mode::OamScan;
+            self.sprites = self.scan_oam();
+        }
+    }
+
+    fn read_tile_map(&self, map: u16, x: u8, y: u8) -> u8 {
+        self.vram.read_byte(map + (y / 8) as u16 * 32 + (x / 8) as u16)
+    }
+
+    // The colour index of a pixel within a tile, with rows past the eighth running into the next tile
+    fn read_tile_pixel(&self, address: u16, x: u8, y: u8) -> u8 {
+        let low = self.vram.read_byte(address + y as u16 * 2);
+        let high = self.vram.read_byte(address + y as u16 * 2 + 1);
+        let bit = 7 - x;
+
+        ((high >> bit) & 0x01) << 1 | ((low >> bit) & 0x01)
+    }
+
+    fn background_tile_address(&self, tile: u8) -> u16 {
+        if self.lcd_control & TILE_DATA != 0 {
+            TILE_DATA_0 + tile as u16 * TILE_SIZE
+        } else {
+            TILE_DATA_1.wrapping_add((tile as i8 as i16 * TILE_SIZE as i16) as u16)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A Y-flipped 8x16 sprite whose ninth row is on line 0, over two solid tiles
+    fn tall_sprite_display(renderer: Renderer) -> Display {
+        let mut display = Display::new(false, renderer);
+
+        for offset in 0..TILE_SIZE * 2 {
+            display.write_vram(offset, 0xFF);
+        }
+
+        display.write_oam(0, 8);
+        display.write_oam(1, 8);
+        display.write_oam(2, 0);
+        display.write_oam(3, 0x40);
+        display.write_register(REG_OBJECT_PALETTE_0, 0xE4);
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE | OBJECT_SIZE | OBJECT_ENABLE);
+
+        display
+    }
+
+    #[test]
+    fn sprites_keep_drawing_at_the_scanned_height() {
+        for &renderer in &[Renderer::Scanline, Renderer::Fifo] {
+            let mut display = tall_sprite_display(renderer);
+
+            display.tick(DOTS_PER_LINE as u32, &mut Interrupts::default());
+            assert_eq!(display.framebuffer[..8], [3; 8], "{:?}", renderer);
         }
     }
+
+    #[test]
+    fn switching_to_8x8_mid_line_drops_rows_past_the_end() {
+        for &renderer in &[Renderer::Scanline, Renderer::Fifo] {
+            let mut display = tall_sprite_display(renderer);
+            let mut interrupts = Interrupts::default();
+
+            display.tick(OAM_SCAN_DOTS as u32, &mut interrupts);
+            display.write_register(REG_LCD_CONTROL, LCD_ENABLE | OBJECT_ENABLE);
+            display.tick((DOTS_PER_LINE - OAM_SCAN_DOTS) as u32, &mut interrupts);
+
+            assert_eq!(display.line, 1);
+            assert_eq!(display.framebuffer[..8], [0; 8], "{:?}", renderer);
+        }
+    }
+
+    // Switched on with the given STAT interrupt selects and LYC
+    fn enabled_display(status: u8, line_compare: u8) -> Display {
+        let mut display = Display::new(false, Renderer::Scanline);
+
+        display.write_register(REG_LCD_STATUS, status);
+        display.write_register(REG_LINE_COMPARE, line_compare);
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE);
+
+        display
+    }
+
+    fn requested(interrupts: &Interrupts, interrupt: Interrupt) -> bool {
+        interrupts.read_flag() & interrupt.mask() != 0
+    }
+
+    #[test]
+    fn vblank_starts_at_line_144() {
+        let mut display = enabled_display(0x00, 0xFF);
+        let mut interrupts = Interrupts::default();
+
+        display.tick(SCREEN_HEIGHT as u32 * DOTS_PER_LINE as u32 - 1, &mut interrupts);
+        assert!(!requested(&interrupts, Interrupt::VBlank));
+        assert_eq!(display.read_register(REG_LINE), 143);
+
+        display.tick(1, &mut interrupts);
+        assert!(requested(&interrupts, Interrupt::VBlank));
+        assert_eq!(display.read_register(REG_LINE), 144);
+        assert_eq!(display.read_register(REG_LCD_STATUS) & 0x03, Mode::VBlank as u8);
+        assert!(display.take_frame().is_some());
+
+        // Ten lines later the next frame starts
+        display.tick(10 * DOTS_PER_LINE as u32, &mut interrupts);
+        assert_eq!(display.read_register(REG_LINE), 0);
+        assert_eq!(display.read_register(REG_LCD_STATUS) & 0x03, Mode::OamScan as u8);
+    }
+
+    #[test]
+    fn coincidence_flag_follows_lyc() {
+        let mut display = enabled_display(0x00, 0x01);
+        let mut interrupts = Interrupts::default();
+
+        assert_eq!(display.read_register(REG_LCD_STATUS) & COINCIDENCE_FLAG, 0);
+
+        display.tick(DOTS_PER_LINE as u32, &mut interrupts);
+        assert_ne!(display.read_register(REG_LCD_STATUS) & COINCIDENCE_FLAG, 0);
+        assert!(!requested(&interrupts, Interrupt::LcdStat));
+
+        display.write_register(REG_LINE_COMPARE, 0x02);
+        assert_eq!(display.read_register(REG_LCD_STATUS) & COINCIDENCE_FLAG, 0);
+    }
+
+    #[test]
+    fn stat_interrupts_on_rising_edges_only() {
+        let mut interrupts = Interrupts::default();
+
+        // LYC alone interrupts as line 1 starts
+        let mut display = enabled_display(COINCIDENCE_SELECT, 0x01);
+
+        display.tick(DOTS_PER_LINE as u32, &mut interrupts);
+        assert!(requested(&interrupts, Interrupt::LcdStat));
+
+        // With HBlank also selected the line is already high from line 0's HBlank,
+        // so only that one interrupts
+        let mut display = enabled_display(HBLANK_SELECT | COINCIDENCE_SELECT, 0x01);
+
+        interrupts.write_flag(0x00);
+        display.tick((OAM_SCAN_DOTS + TRANSFER_DOTS) as u32, &mut interrupts);
+        assert!(requested(&interrupts, Interrupt::LcdStat));
+
+        interrupts.write_flag(0x00);
+        display.tick((DOTS_PER_LINE - OAM_SCAN_DOTS - TRANSFER_DOTS) as u32, &mut interrupts);
+        assert_eq!(display.read_register(REG_LINE), 1);
+        assert!(!requested(&interrupts, Interrupt::LcdStat));
+
+        // Line 1's HBlank keeps it high too
+        display.tick(DOTS_PER_LINE as u32 - 1, &mut interrupts);
+        assert!(!requested(&interrupts, Interrupt::LcdStat));
+
+        // Until line 2's OAM scan drops it, and its HBlank raises it again
+        display.tick(1 + (OAM_SCAN_DOTS + TRANSFER_DOTS) as u32, &mut interrupts);
+        assert!(requested(&interrupts, Interrupt::LcdStat));
+    }
 }
@@ -0,0 +1,265 @@
+use super::{Display, SCREEN_WIDTH, BACKGROUND_ENABLE, BACKGROUND_MAP, WINDOW_ENABLE, WINDOW_MAP,
+            OBJECT_ENABLE, TILE_MAP_0, TILE_MAP_1};
+use super::sprite::SPRITE_X_OFFSET;
+
+// The window's X position is offset by seven, so 7 puts it at the left edge
+pub const WINDOW_X_OFFSET: u8 = 7;
+pub const WINDOW_X_MAX: u8 = 166;
+
+pub fn apply_palette(palette: u8, color: u8) -> u8 {
+    (palette >> (color * 2)) & 0x03
+}
+
+impl Display {
+    // Draws the whole of the current line into the framebuffer in one go, using the
+    // registers as they are at the end of pixel transfer
+    pub(super) fn render_line(&mut self) {
+        let mut colors = [0; SCREEN_WIDTH];
+
+        if self.lcd_control & BACKGROUND_ENABLE != 0 {
+            self.render_background(&mut colors);
+            self.render_window(&mut colors);
+        }
+
+        let row = self.line as usize * SCREEN_WIDTH;
+
+        for (x, &color) in colors.iter().enumerate() {
+            self.framebuffer[row + x] = apply_palette(self.background_palette, color);
+        }
+
+        if self.lcd_control & OBJECT_ENABLE != 0 {
+            self.render_sprites(&colors);
+        }
+    }
+
+    fn render_background(&self, colors: &mut [u8; SCREEN_WIDTH]) {
+        let map = if self.lcd_control & BACKGROUND_MAP != 0 { TILE_MAP_1 } else { TILE_MAP_0 };
+        let y = self.scroll_y.wrapping_add(self.line);
+
+        for (screen_x, color) in colors.iter_mut().enumerate() {
+            let x = self.scroll_x.wrapping_add(screen_x as u8);
+            let tile = self.background_tile_address(self.read_tile_map(map, x, y));
+
+            *color = self.read_tile_pixel(tile, x % 8, y % 8);
+        }
+    }
+
+    fn render_window(&mut self, colors: &mut [u8; SCREEN_WIDTH]) {
+        if self.lcd_control & WINDOW_ENABLE == 0 || self.line < self.window_y || self.window_x > WINDOW_X_MAX {
+            return;
+        }
+
+        let map = if self.lcd_control & WINDOW_MAP != 0 { TILE_MAP_1 } else { TILE_MAP_0 };
+        let start = self.window_x as i16 - WINDOW_X_OFFSET as i16;
+        let y = self.window_line;
+
+        for (screen_x, color) in colors.iter_mut().enumerate().skip(start.max(0) as usize) {
+            let x = (screen_x as i16 - start) as u8;
+            let tile = self.background_tile_address(self.read_tile_map(map, x, y));
+
+            *color = self.read_tile_pixel(tile, x % 8, y % 8);
+        }
+
+        self.window_line += 1;
+    }
+
+    fn render_sprites(&mut self, background: &[u8; SCREEN_WIDTH]) {
+        let row = self.line as usize * SCREEN_WIDTH;
+        let mut drawn = [false; SCREEN_WIDTH];
+
+        // Sprites are in priority order, so the first opaque pixel at each X wins,
+        // even when it ends up hidden behind the background
+        for sprite in &self.sprites {
+            let (address, y) = match self.sprite_row(sprite) {
+                Some(row) => row,
+                None => continue,
+            };
+
+            for x in 0..8 {
+                let screen_x = sprite.x as i16 - SPRITE_X_OFFSET as i16 + x as i16;
+
+                if screen_x < 0 || screen_x as usize >= SCREEN_WIDTH || drawn[screen_x as usize] {
+                    continue;
+                }
+
+                let screen_x = screen_x as usize;
+                let color = self.read_tile_pixel(address, if sprite.flip_x() { 7 - x } else { x }, y);
+
+                if color == 0 {
+                    continue;
+                }
+
+                drawn[screen_x] = true;
+
+                if !(sprite.behind_background() && background[screen_x] != 0) {
+                    self.framebuffer[row + screen_x] = apply_palette(self.object_palettes[sprite.palette()], color);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::{Renderer, REG_LCD_CONTROL, REG_SCROLL_X, REG_SCROLL_Y, REG_WINDOW_X, REG_WINDOW_Y,
+                       REG_BACKGROUND_PALETTE, REG_OBJECT_PALETTE_0, DOTS_PER_LINE, LCD_ENABLE, TILE_DATA,
+                       TILE_DATA_0, TILE_DATA_1, TILE_SIZE};
+    use super::super::sprite::SPRITE_Y_OFFSET;
+    use super::super::super::interrupts::Interrupts;
+
+    fn display() -> Display {
+        let mut display = Display::new(false, Renderer::Scanline);
+
+        display.write_register(REG_BACKGROUND_PALETTE, 0xE4);
+        display.write_register(REG_OBJECT_PALETTE_0, 0xE4);
+
+        display
+    }
+
+    // Fills the tile at the given VRAM offset with a single colour
+    fn solid_tile(display: &mut Display, address: u16, color: u8) {
+        for row in 0..8 {
+            display.write_vram(address + row * 2, if color & 0x01 != 0 { 0xFF } else { 0x00 });
+            display.write_vram(address + row * 2 + 1, if color & 0x02 != 0 { 0xFF } else { 0x00 });
+        }
+    }
+
+    fn sprite(display: &mut Display, index: u16, x: u8, tile: u8) {
+        display.write_oam(index * 4, SPRITE_Y_OFFSET);
+        display.write_oam(index * 4 + 1, x);
+        display.write_oam(index * 4 + 2, tile);
+    }
+
+    fn run_lines(display: &mut Display, lines: u32) {
+        display.tick(lines * DOTS_PER_LINE as u32, &mut Interrupts::default());
+    }
+
+    fn line(display: &Display, line: usize) -> &[u8] {
+        &display.framebuffer[line * SCREEN_WIDTH..(line + 1) * SCREEN_WIDTH]
+    }
+
+    #[test]
+    fn tile_data_is_addressed_signed_or_unsigned() {
+        for &(lcd_control, shade) in &[(TILE_DATA, 1), (0, 2)] {
+            let mut display = display();
+
+            solid_tile(&mut display, TILE_DATA_0 + TILE_SIZE, 1);
+            solid_tile(&mut display, TILE_DATA_1 + TILE_SIZE, 2);
+            display.write_vram(TILE_MAP_0, 0x01);
+            display.write_register(REG_LCD_CONTROL, LCD_ENABLE | BACKGROUND_ENABLE | lcd_control);
+            run_lines(&mut display, 1);
+
+            assert_eq!(line(&display, 0)[..8], [shade; 8]);
+            assert_eq!(line(&display, 0)[8], 0);
+        }
+    }
+
+    #[test]
+    fn scrolling_wraps_around_the_map() {
+        let mut display = display();
+
+        // Only the top left tile of the map is coloured
+        solid_tile(&mut display, TILE_DATA_0 + TILE_SIZE, 3);
+        display.write_vram(TILE_MAP_0, 0x01);
+        display.write_register(REG_SCROLL_X, 252);
+        display.write_register(REG_SCROLL_Y, 248);
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE | BACKGROUND_ENABLE | TILE_DATA);
+        run_lines(&mut display, 9);
+
+        assert!(line(&display, 7).iter().all(|&shade| shade == 0));
+        assert_eq!(line(&display, 8)[..4], [0; 4]);
+        assert_eq!(line(&display, 8)[4..12], [3; 8]);
+        assert!(line(&display, 8)[12..].iter().all(|&shade| shade == 0));
+    }
+
+    #[test]
+    fn window_is_placed_by_wx_and_wy() {
+        let mut display = display();
+
+        solid_tile(&mut display, TILE_DATA_0 + TILE_SIZE, 3);
+
+        for offset in 0..32 * 32 {
+            display.write_vram(TILE_MAP_1 + offset, 0x01);
+        }
+
+        display.write_register(REG_WINDOW_X, 80 + WINDOW_X_OFFSET);
+        display.write_register(REG_WINDOW_Y, 2);
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE | BACKGROUND_ENABLE | TILE_DATA | WINDOW_ENABLE | WINDOW_MAP);
+        run_lines(&mut display, 3);
+
+        assert!(line(&display, 1).iter().all(|&shade| shade == 0));
+        assert_eq!(line(&display, 2)[..80], [0; 80][..]);
+        assert_eq!(line(&display, 2)[80..], [3; 80][..]);
+    }
+
+    #[test]
+    fn window_line_only_counts_lines_with_the_window() {
+        let mut display = display();
+        let lcd_control = LCD_ENABLE | BACKGROUND_ENABLE | TILE_DATA | WINDOW_MAP;
+
+        // The first window row of tiles is colour 3, the third colour 1
+        solid_tile(&mut display, TILE_DATA_0 + TILE_SIZE, 3);
+        solid_tile(&mut display, TILE_DATA_0 + TILE_SIZE * 2, 1);
+
+        for column in 0..32 {
+            display.write_vram(TILE_MAP_1 + column, 0x01);
+            display.write_vram(TILE_MAP_1 + 64 + column, 0x02);
+        }
+
+        display.write_register(REG_WINDOW_X, WINDOW_X_OFFSET);
+        display.write_register(REG_LCD_CONTROL, lcd_control | WINDOW_ENABLE);
+        run_lines(&mut display, 4);
+        assert_eq!(display.window_line, 4);
+
+        display.write_register(REG_LCD_CONTROL, lcd_control);
+        run_lines(&mut display, 16);
+        assert_eq!(display.window_line, 4);
+
+        // Line 20 carries on from the window's fifth line, not its twenty-first
+        display.write_register(REG_LCD_CONTROL, lcd_control | WINDOW_ENABLE);
+        run_lines(&mut display, 1);
+        assert_eq!(display.window_line, 5);
+        assert!(line(&display, 20).iter().all(|&shade| shade == 3));
+    }
+
+    #[test]
+    fn only_ten_sprites_per_line() {
+        let mut display = display();
+
+        solid_tile(&mut display, TILE_DATA_0 + TILE_SIZE, 3);
+
+        for index in 0..11 {
+            sprite(&mut display, index, SPRITE_X_OFFSET + index as u8 * 8, 0x01);
+        }
+
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE | OBJECT_ENABLE);
+        run_lines(&mut display, 1);
+
+        assert_eq!(line(&display, 0)[..80], [3; 80][..]);
+        assert_eq!(line(&display, 0)[80..88], [0; 8]);
+    }
+
+    #[test]
+    fn lower_x_then_oam_order_wins() {
+        let mut display = display();
+
+        solid_tile(&mut display, TILE_DATA_0 + TILE_SIZE, 1);
+        solid_tile(&mut display, TILE_DATA_0 + TILE_SIZE * 2, 2);
+
+        // Later in OAM but further left, so on top where they overlap
+        sprite(&mut display, 0, 12, 0x01);
+        sprite(&mut display, 1, 8, 0x02);
+
+        // At the same X the first in OAM is on top
+        sprite(&mut display, 2, 40, 0x01);
+        sprite(&mut display, 3, 40, 0x02);
+
+        display.write_register(REG_LCD_CONTROL, LCD_ENABLE | OBJECT_ENABLE);
+        run_lines(&mut display, 1);
+
+        assert_eq!(line(&display, 0)[..8], [2; 8]);
+        assert_eq!(line(&display, 0)[8..12], [1; 4]);
+        assert_eq!(line(&display, 0)[32..40], [1; 8]);
+    }
+}
@@ -0,0 +1,89 @@
+use super::{Display, OBJECT_SIZE, TILE_DATA_0, TILE_SIZE};
+
+use super::super::memory::Memory;
+
+const SPRITES: u16 = 40;
+const SPRITES_PER_LINE: usize = 10;
+
+// Sprite coordinates are offset so they can sit partly off the top and left edges
+pub const SPRITE_Y_OFFSET: u8 = 16;
+pub const SPRITE_X_OFFSET: u8 = 8;
+
+const BACKGROUND_PRIORITY: u8 = 0x80;
+const FLIP_Y: u8 = 0x40;
+const FLIP_X: u8 = 0x20;
+const PALETTE: u8 = 0x10;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Sprite {
+    pub y: u8,
+    pub x: u8,
+    pub tile: u8,
+    pub attributes: u8,
+}
+
+impl Sprite {
+    pub fn behind_background(&self) -> bool {
+        self.attributes & BACKGROUND_PRIORITY != 0
+    }
+
+    pub fn flip_x(&self) -> bool {
+        self.attributes & FLIP_X != 0
+    }
+
+    pub fn flip_y(&self) -> bool {
+        self.attributes & FLIP_Y != 0
+    }
+
+    pub fn palette(&self) -> usize {
+        if self.attributes & PALETTE != 0 { 1 } else { 0 }
+    }
+}
+
+impl Display {
+    pub(super) fn sprite_height(&self) -> u8 {
+        if self.lcd_control & OBJECT_SIZE != 0 { 16 } else { 8 }
+    }
+
+    // The address of the sprite's tile and its row on the current line. Sprites are picked
+    // with the height at the OAM scan, so a switch to 8x8 since can leave the line below one
+    pub(super) fn sprite_row(&self, sprite: &Sprite) -> Option<(u16, u8)> {
+        let height = self.sprite_height();
+        let mut y = self.line + SPRITE_Y_OFFSET - sprite.y;
+
+        if y >= height {
+            return None;
+        }
+
+        if sprite.flip_y() {
+            y = height - 1 - y;
+        }
+
+        let tile = if height == 16 { sprite.tile & 0xFE } else { sprite.tile };
+
+        Some((TILE_DATA_0 + tile as u16 * TILE_SIZE, y))
+    }
+
+    // Finds the first ten sprites in OAM overlapping the current line, ordered
+    // by drawing priority: the lowest X first, then the lowest OAM index
+    pub(super) fn scan_oam(&self) -> Vec<Sprite> {
+        let line = self.line + SPRITE_Y_OFFSET;
+        let height = self.sprite_height();
+
+        let mut sprites: Vec<Sprite> = (0..SPRITES)
+            .map(|index| Sprite {
+                y: self.oam.read_byte(index * 4),
+                x: self.oam.read_byte(index * 4 + 1),
+                tile: self.oam.read_byte(index * 4 + 2),
+                attributes: self.oam.read_byte(index * 4 + 3),
+            })
+            .filter(|sprite| line >= sprite.y && line < sprite.y.wrapping_add(height))
+            .take(SPRITES_PER_LINE)
+            .collect();
+
+        // A stable sort keeps OAM order among sprites at the same X
+        sprites.sort_by_key(|sprite| sprite.x);
+
+        sprites
+    }
+}
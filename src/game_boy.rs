@@ -1,28 +1,161 @@
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use super::processor;
-// use super::display;
 use super::memory::MemoryMap;
+use super::cartridge::Cartridge;
+use super::model::Model;
+use super::display::Renderer;
+use super::joypad::Button;
+use super::serial::SerialDevice;
 
 pub struct GameBoy {
   processor: processor::Processor<MemoryMap>,
-  // display: display::Display<MemoryMap>,
 }
 
 impl GameBoy {
-  pub fn new(bootrom: Box<[u8]>, gamerom: Box<[u8]>) -> GameBoy {
-    let memory_map = MemoryMap::new(bootrom, gamerom);
+  pub fn new(model: Model, renderer: Renderer, bootrom: Box<[u8]>, cartridge: Cartridge) -> GameBoy {
+    let memory_map = MemoryMap::new(model, renderer, bootrom, cartridge);
 
     GameBoy {
       processor: processor::Processor::new(memory_map),
-      // display: display::Display::new(memory_map),
     }
   }
 
-  pub fn run(&mut self) {
-    let mut tick = 0;
-    loop {
-      self.processor.step();
-      print!("GameBoy: {}\n{:?}", tick, self.processor);
-      tick += 1;
+  // Starts straight at the cartridge's entry point, as if the model's bootrom had already run
+  pub fn without_bootrom(model: Model, renderer: Renderer, cartridge: Cartridge) -> GameBoy {
+    let registers = model.post_boot_registers(cartridge.header().header_checksum);
+    let mut memory_map = MemoryMap::new(model, renderer, Box::new([]), cartridge);
+
+    memory_map.skip_bootrom();
+
+    GameBoy {
+      processor: processor::Processor::with_registers(processor::Registers::post_boot(registers), memory_map),
+    }
+  }
+
+  // Runs until the stop flag is raised, such as from a Ctrl-C handler
+  pub fn run(&mut self, stop: &AtomicBool) {
+    while !stop.load(Ordering::Relaxed) {
+      let cycles = self.processor.step();
+      print!("GameBoy: {} (+{})\n{:?}", self.processor.cycles(), cycles, self.processor);
+    }
+  }
+
+  pub fn press(&mut self, button: Button) {
+    self.processor.memory_mut().press(button);
+  }
+
+  pub fn release(&mut self, button: Button) {
+    self.processor.memory_mut().release(button);
+  }
+
+  // Plugs a device into the link port in place of the default, which prints to stdout
+  pub fn connect_serial(&mut self, device: Box<dyn SerialDevice>) {
+    self.processor.memory_mut().connect_serial(device);
+  }
+
+  // Every byte the game has sent over the link port
+  pub fn serial_output(&self) -> &[u8] {
+    self.processor.memory().serial_output()
+  }
+
+  // Lets the CPU access VRAM and OAM whatever the PPU is doing, for debugging
+  pub fn set_access_blocking(&mut self, enabled: bool) {
+    self.processor.memory_mut().set_access_blocking(enabled);
+  }
+
+  // The 160x144 picture, as shades from 0 (white) to 3 (black), whenever a new frame has been completed
+  pub fn frame(&mut self) -> Option<&[u8]> {
+    self.processor.memory_mut().take_frame()
+  }
+
+  // Writes battery-backed RAM out to the save file straight away
+  pub fn flush(&mut self) -> io::Result<()> {
+    self.processor.memory_mut().flush_cartridge()
+  }
+
+  // Whether the cartridge's rumble motor is running
+  pub fn rumble(&self) -> bool {
+    self.processor.memory().rumble()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::cartridge::{self, ClockSource};
+  use super::super::interrupts::Interrupt;
+  use super::super::memory::{Memory, JOYPAD_REG, INTERRUPT_FLAG_REG};
+
+  const SERIAL_DATA_REG: u16 = 0xFF01;
+  const SERIAL_CONTROL_REG: u16 = 0xFF02;
+
+  // Nothing plugged in, without printing what's sent
+  struct Silent;
+
+  impl SerialDevice for Silent {
+    fn transfer(&mut self, _value: u8) -> Option<u8> {
+      Some(0xFF)
     }
   }
+
+  fn game_boy(cartridge_type: u8) -> GameBoy {
+    let rom = cartridge::test_rom(cartridge_type, 0x00, 0x00).into_boxed_slice();
+    let cartridge = Cartridge::new(rom, ClockSource::Emulated).unwrap();
+
+    GameBoy::without_bootrom(Model::Dmg, Renderer::Scanline, cartridge)
+  }
+
+  #[test]
+  fn buttons_reach_the_joypad_register() {
+    let mut game_boy = game_boy(0x00);
+
+    // Select the action buttons
+    game_boy.processor.memory_mut().write_byte(JOYPAD_REG, 0x10);
+    game_boy.processor.memory_mut().write_byte(INTERRUPT_FLAG_REG, 0x00);
+
+    game_boy.press(Button::Start);
+    assert_eq!(game_boy.processor.memory().read_byte(JOYPAD_REG), 0xD7);
+    assert_ne!(game_boy.processor.memory().read_byte(INTERRUPT_FLAG_REG) & Interrupt::Joypad.mask(), 0);
+
+    game_boy.release(Button::Start);
+    assert_eq!(game_boy.processor.memory().read_byte(JOYPAD_REG), 0xDF);
+  }
+
+  #[test]
+  fn serial_output_collects_sent_bytes() {
+    let mut game_boy = game_boy(0x00);
+
+    game_boy.connect_serial(Box::new(Silent));
+    game_boy.processor.memory_mut().write_byte(SERIAL_DATA_REG, b'A');
+    game_boy.processor.memory_mut().write_byte(SERIAL_CONTROL_REG, 0x81);
+    game_boy.processor.memory_mut().tick(4096);
+
+    assert_eq!(game_boy.serial_output(), b"A");
+    assert_eq!(game_boy.processor.memory().read_byte(SERIAL_CONTROL_REG) & 0x80, 0);
+    assert_ne!(game_boy.processor.memory().read_byte(INTERRUPT_FLAG_REG) & Interrupt::Serial.mask(), 0);
+  }
+
+  #[test]
+  fn run_returns_once_stopped() {
+    let mut game_boy = game_boy(0x00);
+
+    game_boy.run(&AtomicBool::new(true));
+    assert_eq!(game_boy.processor.cycles(), 0);
+  }
+
+  #[test]
+  fn rumble_follows_the_cartridge_motor() {
+    // MBC5 with rumble
+    let mut game_boy = game_boy(0x1C);
+
+    assert!(!game_boy.rumble());
+
+    game_boy.processor.memory_mut().write_byte(0x4000, 0x08);
+    assert!(game_boy.rumble());
+
+    game_boy.processor.memory_mut().write_byte(0x4000, 0x00);
+    assert!(!game_boy.rumble());
+  }
 }
@@ -0,0 +1,63 @@
+// Interrupt sources in priority order, highest first
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interrupt {
+  VBlank,
+  LcdStat,
+  Timer,
+  Serial,
+  Joypad,
+}
+
+const INTERRUPTS: [Interrupt; 5] = [
+  Interrupt::VBlank,
+  Interrupt::LcdStat,
+  Interrupt::Timer,
+  Interrupt::Serial,
+  Interrupt::Joypad,
+];
+
+// IF only implements the lower five bits, the rest read back as 1
+const INTERRUPT_FLAG_UNUSED: u8 = 0xE0;
+
+impl Interrupt {
+  // Picks the highest priority interrupt out of a set of IE/IF bits
+  pub fn from_bits(bits: u8) -> Option<Interrupt> {
+    INTERRUPTS.iter().cloned().find(|interrupt| bits & interrupt.mask() != 0)
+  }
+
+  pub fn mask(&self) -> u8 {
+    1 << (*self as u8)
+  }
+
+  pub fn vector(&self) -> u16 {
+    0x0040 + 0x08 * (*self as u16)
+  }
+}
+
+#[derive(Default)]
+pub struct Interrupts {
+  enable: u8, // IE
+  flag: u8, // IF
+}
+
+impl Interrupts {
+  pub fn request(&mut self, interrupt: Interrupt) {
+    self.flag |= interrupt.mask();
+  }
+
+  pub fn read_enable(&self) -> u8 {
+    self.enable
+  }
+
+  pub fn write_enable(&mut self, value: u8) {
+    self.enable = value;
+  }
+
+  pub fn read_flag(&self) -> u8 {
+    self.flag | INTERRUPT_FLAG_UNUSED
+  }
+
+  pub fn write_flag(&mut self, value: u8) {
+    self.flag = value & !INTERRUPT_FLAG_UNUSED;
+  }
+}
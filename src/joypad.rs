@@ -0,0 +1,149 @@
+use super::interrupts::{Interrupt, Interrupts};
+
+// Bits 4 and 5 of P1 select which button lines are read back in the low nibble
+const SELECT_MASK: u8 = 0x30;
+const SELECT_DIRECTIONS: u8 = 0x10;
+const SELECT_ACTIONS: u8 = 0x20;
+const JOYPAD_UNUSED: u8 = 0xC0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Button {
+  Right,
+  Left,
+  Up,
+  Down,
+  A,
+  B,
+  Select,
+  Start,
+}
+
+impl Button {
+  // Directions sit in the low nibble and actions in the high one, each in the order they're read back
+  fn mask(self) -> u8 {
+    1 << (self as u8)
+  }
+}
+
+#[derive(Default)]
+pub struct Joypad {
+  select: u8,
+  pressed: u8,
+}
+
+impl Joypad {
+  pub fn press(&mut self, button: Button, interrupts: &mut Interrupts) {
+    let lines = self.lines();
+
+    self.pressed |= button.mask();
+    self.update(lines, interrupts);
+  }
+
+  pub fn release(&mut self, button: Button) {
+    self.pressed &= !button.mask();
+  }
+
+  pub fn read(&self) -> u8 {
+    JOYPAD_UNUSED | self.select | self.lines()
+  }
+
+  // Selecting a line a button is held on pulls it low, just like pressing it
+  pub fn write(&mut self, value: u8, interrupts: &mut Interrupts) {
+    let lines = self.lines();
+
+    self.select = value & SELECT_MASK;
+    self.update(lines, interrupts);
+  }
+
+  // The input lines, which read as 0 for pressed buttons on a selected (low) group
+  fn lines(&self) -> u8 {
+    let mut pressed = 0;
+
+    if self.select & SELECT_DIRECTIONS == 0 { pressed |= self.pressed & 0x0F; }
+    if self.select & SELECT_ACTIONS == 0 { pressed |= self.pressed >> 4; }
+
+    !pressed & 0x0F
+  }
+
+  // The interrupt fires whenever a line goes from high to low
+  fn update(&self, previous: u8, interrupts: &mut Interrupts) {
+    if previous & !self.lines() != 0 {
+      interrupts.request(Interrupt::Joypad);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn joypad_interrupt(interrupts: &Interrupts) -> bool {
+    interrupts.read_flag() & Interrupt::Joypad.mask() != 0
+  }
+
+  #[test]
+  fn reads_released_buttons_as_high() {
+    let mut interrupts = Interrupts::default();
+    let mut joypad = Joypad::default();
+
+    joypad.write(0x00, &mut interrupts);
+    assert_eq!(joypad.read(), 0xCF);
+
+    joypad.write(0x30, &mut interrupts);
+    assert_eq!(joypad.read(), 0xFF);
+  }
+
+  #[test]
+  fn select_lines_choose_the_group_read_back() {
+    let mut interrupts = Interrupts::default();
+    let mut joypad = Joypad::default();
+
+    joypad.press(Button::Down, &mut interrupts);
+    joypad.press(Button::A, &mut interrupts);
+
+    joypad.write(SELECT_ACTIONS, &mut interrupts);
+    assert_eq!(joypad.read(), 0xE7);
+
+    joypad.write(SELECT_DIRECTIONS, &mut interrupts);
+    assert_eq!(joypad.read(), 0xDE);
+
+    // With both groups selected the lines are ANDed together
+    joypad.write(0x00, &mut interrupts);
+    assert_eq!(joypad.read(), 0xC6);
+
+    joypad.write(SELECT_MASK, &mut interrupts);
+    assert_eq!(joypad.read(), 0xFF);
+
+    joypad.release(Button::Down);
+    joypad.write(SELECT_ACTIONS, &mut interrupts);
+    assert_eq!(joypad.read(), 0xEF);
+  }
+
+  #[test]
+  fn interrupt_fires_when_a_line_goes_low() {
+    let mut interrupts = Interrupts::default();
+    let mut joypad = Joypad::default();
+
+    // Only the directions are selected, so B doesn't reach the lines
+    joypad.write(SELECT_ACTIONS, &mut interrupts);
+    joypad.press(Button::B, &mut interrupts);
+    assert!(!joypad_interrupt(&interrupts));
+
+    joypad.press(Button::Up, &mut interrupts);
+    assert!(joypad_interrupt(&interrupts));
+
+    // Releasing takes the line high again, which doesn't
+    interrupts.write_flag(0x00);
+    joypad.release(Button::Up);
+    assert!(!joypad_interrupt(&interrupts));
+
+    // Selecting the actions with B held pulls its line low too
+    joypad.write(SELECT_DIRECTIONS, &mut interrupts);
+    assert!(joypad_interrupt(&interrupts));
+
+    // Directions are no longer selected, so pressing one changes nothing
+    interrupts.write_flag(0x00);
+    joypad.press(Button::Left, &mut interrupts);
+    assert!(!joypad_interrupt(&interrupts));
+  }
+}
@@ -0,0 +1,18 @@
+extern crate byteorder;
+extern crate num;
+#[macro_use]
+extern crate enum_primitive;
+
+mod processor;
+pub mod display;
+pub mod game_boy;
+mod memory;
+pub mod joypad;
+pub mod serial;
+mod timer;
+mod sound;
+mod interrupts;
+pub mod cartridge;
+pub mod model;
+pub mod link_cable;
+pub mod printer;
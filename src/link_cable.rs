@@ -0,0 +1,263 @@
+use std::io::{self, ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::serial::SerialDevice;
+
+// Both ends swap what they are doing on the link port this often, and transfers only
+// complete when they do, so each Game Boy sees them at the same cycle however fast the
+// other one runs. This is one bit at the normal internal clock
+const SYNC_CYCLES: u32 = 512;
+
+const CLOCKED_FLAG: u8 = 0x01; // Sent a byte with the internal clock
+const WAITING_FLAG: u8 = 0x02; // Has a byte waiting for an external clock
+
+// Addresses with this prefix are Unix socket paths rather than TCP host:port pairs
+const UNIX_PREFIX: &str = "unix:";
+
+// How long a sync waits on the other end before checking whether to stop
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+
+pub trait Stream: Read + Write + Send {
+  fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+}
+
+impl Stream for TcpStream {
+  fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+    TcpStream::set_read_timeout(self, timeout)
+  }
+}
+
+#[cfg(unix)]
+impl Stream for UnixStream {
+  fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+    UnixStream::set_read_timeout(self, timeout)
+  }
+}
+
+// Connects two Game Boys, whichever of them drives the clock
+pub struct LinkCable {
+  stream: Option<Box<dyn Stream>>, // None once the other end has gone away
+  cycles: u32, // Since the last sync
+  clocked: Option<u8>, // Sent with the internal clock since the last sync
+  reply: Option<u8>, // To the byte clocked out, once the other end has answered
+  stop: Option<&'static AtomicBool>, // Gives up waiting on the other end once raised
+}
+
+impl LinkCable {
+  pub fn new<S: Stream + 'static>(stream: S) -> io::Result<LinkCable> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+
+    Ok(LinkCable {
+      stream: Some(Box::new(stream)),
+      cycles: 0,
+      clocked: None,
+      reply: None,
+      stop: None,
+    })
+  }
+
+  // A sync blocks until the other end answers, so without this a stalled peer would keep
+  // the run loop from ever seeing its stop flag
+  pub fn set_stop_flag(&mut self, stop: &'static AtomicBool) {
+    self.stop = Some(stop);
+  }
+
+  // Both ends of a cable, for linking Game Boys running on separate threads of this process
+  #[cfg(unix)]
+  pub fn pair() -> io::Result<(LinkCable, LinkCable)> {
+    let (first, second) = UnixStream::pair()?;
+
+    Ok((LinkCable::new(first)?, LinkCable::new(second)?))
+  }
+
+  // Waits for the other Game Boy to connect, on a TCP address or a Unix socket path
+  pub fn listen(address: &str) -> io::Result<LinkCable> {
+    #[cfg(unix)]
+    {
+      if let Some(path) = address.strip_prefix(UNIX_PREFIX) {
+        let (stream, _) = UnixListener::bind(path)?.accept()?;
+
+        return LinkCable::new(stream);
+      }
+    }
+
+    let (stream, _) = TcpListener::bind(address)?.accept()?;
+
+    LinkCable::with_tcp(stream)
+  }
+
+  pub fn connect(address: &str) -> io::Result<LinkCable> {
+    #[cfg(unix)]
+    {
+      if let Some(path) = address.strip_prefix(UNIX_PREFIX) {
+        return LinkCable::new(UnixStream::connect(path)?);
+      }
+    }
+
+    LinkCable::with_tcp(TcpStream::connect(address)?)
+  }
+
+  // Syncs are tiny and each end waits on the other's, so they can't sit in a buffer
+  fn with_tcp(stream: TcpStream) -> io::Result<LinkCable> {
+    stream.set_nodelay(true)?;
+
+    LinkCable::new(stream)
+  }
+
+  fn sync(&mut self, message: [u8; 3]) -> Option<[u8; 3]> {
+    let mut reply = [0; 3];
+    let stop = self.stop;
+
+    let result = match self.stream {
+      Some(ref mut stream) => stream.write_all(&message).and_then(|_| read_reply(stream, &mut reply, stop)),
+      None => return None,
+    };
+
+    match result {
+      Ok(()) => Some(reply),
+      Err(error) => {
+        eprintln!("Link cable disconnected: {}", error);
+        self.stream = None;
+
+        None
+      }
+    }
+  }
+}
+
+// Like read_exact, but keeps what has arrived when the read times out, and checks the stop
+// flag each time it does
+fn read_reply(stream: &mut Box<dyn Stream>, reply: &mut [u8], stop: Option<&AtomicBool>) -> io::Result<()> {
+  let mut filled = 0;
+
+  while filled < reply.len() {
+    match stream.read(&mut reply[filled..]) {
+      Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+      Ok(count) => filled += count,
+      Err(ref error) if error.kind() == ErrorKind::WouldBlock || error.kind() == ErrorKind::TimedOut => {
+        if stop.is_some_and(|stop| stop.load(Ordering::Relaxed)) {
+          return Err(io::Error::new(ErrorKind::Interrupted, "stopped"));
+        }
+      }
+      Err(ref error) if error.kind() == ErrorKind::Interrupted => {}
+      Err(error) => return Err(error),
+    }
+  }
+
+  Ok(())
+}
+
+impl SerialDevice for LinkCable {
+  // Held back until the next sync, when the other end shifts its byte in return
+  fn transfer(&mut self, value: u8) -> Option<u8> {
+    if self.stream.is_none() {
+      return Some(0xFF);
+    }
+
+    if let Some(reply) = self.reply.take() {
+      return Some(reply);
+    }
+
+    self.clocked = Some(value);
+
+    None
+  }
+
+  fn tick(&mut self, cycles: u32, waiting: Option<u8>) -> Option<u8> {
+    self.cycles += cycles;
+
+    if self.cycles < SYNC_CYCLES {
+      return None;
+    }
+
+    self.cycles -= SYNC_CYCLES;
+
+    let mut flags = 0;
+
+    if self.clocked.is_some() {
+      flags |= CLOCKED_FLAG;
+    }
+
+    if waiting.is_some() {
+      flags |= WAITING_FLAG;
+    }
+
+    let [other_flags, other_clocked, other_waiting] = self.sync([flags, self.clocked.unwrap_or(0xFF), waiting.unwrap_or(0xFF)])?;
+
+    // Nothing shifts back in if the other end isn't expecting a transfer
+    if self.clocked.take().is_some() {
+      self.reply = Some(if other_flags & WAITING_FLAG != 0 { other_waiting } else { 0xFF });
+    }
+
+    if other_flags & CLOCKED_FLAG != 0 && waiting.is_some() {
+      Some(other_clocked)
+    } else {
+      None
+    }
+  }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+  use std::thread;
+
+  use super::super::interrupts::Interrupts;
+  use super::super::serial::Serial;
+
+  const SERIAL_DATA_REG: u16 = 0xFF01;
+  const SERIAL_CONTROL_REG: u16 = 0xFF02;
+
+  // Starts a transfer and runs the port for a while, returning the byte received and the
+  // cycle it arrived on
+  fn transfer(cable: LinkCable, data: u8, control: u8) -> (u8, Option<u32>) {
+    let mut interrupts = Interrupts::default();
+    let mut serial = Serial::new(false);
+    let mut completed = None;
+
+    serial.connect(Box::new(cable));
+    serial.write_register(SERIAL_DATA_REG, data);
+    serial.write_register(SERIAL_CONTROL_REG, control);
+
+    for cycle in 1..=4 * SYNC_CYCLES * 8 {
+      serial.tick(1, &mut interrupts);
+
+      if completed.is_none() && serial.read_register(SERIAL_CONTROL_REG) & 0x80 == 0 {
+        completed = Some(cycle);
+      }
+    }
+
+    assert_eq!(serial.output(), [data]);
+
+    (serial.read_register(SERIAL_DATA_REG), completed)
+  }
+
+  #[test]
+  fn both_ends_receive_at_the_same_sync() {
+    let (first, second) = LinkCable::pair().unwrap();
+
+    let clocked = thread::spawn(move || transfer(first, 0x12, 0x81));
+    let (received, completed) = transfer(second, 0x34, 0x80);
+
+    assert_eq!(received, 0x12);
+    assert_eq!(clocked.join().unwrap(), (0x34, completed));
+    assert!(completed.is_some());
+  }
+
+  #[test]
+  fn a_stalled_sync_gives_up_once_stopped() {
+    static STOP: AtomicBool = AtomicBool::new(true);
+
+    let (mut cable, _other) = LinkCable::pair().unwrap();
+
+    cable.set_stop_flag(&STOP);
+
+    assert_eq!(cable.tick(SYNC_CYCLES, None), None);
+    assert!(cable.stream.is_none());
+    assert_eq!(cable.transfer(0x12), Some(0xFF));
+  }
+}
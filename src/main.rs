@@ -1,29 +1,138 @@
-extern crate byteorder;
-extern crate num;
-#[macro_use]
-extern crate enum_primitive;
-
-mod processor;
-mod display;
-mod game_boy;
-mod memory;
-mod io_ports;
+extern crate ctrlc;
+extern crate rustboy;
 
 use std::env;
 use std::fs;
 use std::io::Read;
 use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rustboy::cartridge::{Cartridge, ClockSource};
+use rustboy::model::Model;
+use rustboy::display::Renderer;
+use rustboy::game_boy::GameBoy;
+use rustboy::link_cable::LinkCable;
+use rustboy::printer::Printer;
+
+// Raised by Ctrl-C, so the save file is written before exiting
+static STOP: AtomicBool = AtomicBool::new(false);
+
+const USAGE: &str = "Usage: rustboy [--model=<dmg0|dmg|mgb|sgb|sgb2|cgb|agb>] [--renderer=<scanline|fifo>] [--no-access-blocking] [--link-listen=<address>|--link-connect=<address>|--printer=<directory>] [bootrom] <gamerom>";
 
 fn main() {
-  let bootrom_name = env::args().nth(1).unwrap();
-  let gamerom_name = env::args().nth(2).unwrap();
+  let mut model = Model::Dmg;
+  let mut renderer = Renderer::Scanline;
+  let mut access_blocking = true;
+  let mut link = None;
+  let mut printer = None;
+  let mut files = Vec::new();
+
+  for arg in env::args().skip(1) {
+    if let Some(name) = arg.strip_prefix("--model=") {
+      model = Model::from_name(name).unwrap_or_else(|| {
+        eprintln!("Unknown model: {}", name);
+        process::exit(1);
+      });
+    } else if let Some(name) = arg.strip_prefix("--renderer=") {
+      renderer = match name {
+        "scanline" => Renderer::Scanline,
+        "fifo" => Renderer::Fifo,
+        _ => {
+          eprintln!("Unknown renderer: {}", name);
+          process::exit(1);
+        }
+      };
+    } else if arg == "--no-access-blocking" {
+      access_blocking = false;
+    } else if let Some(address) = arg.strip_prefix("--link-listen=") {
+      link = Some((true, address.to_string()));
+    } else if let Some(address) = arg.strip_prefix("--link-connect=") {
+      link = Some((false, address.to_string()));
+    } else if let Some(directory) = arg.strip_prefix("--printer=") {
+      printer = Some(directory.to_string());
+    } else {
+      files.push(arg);
+    }
+  }
+
+  // Both plug into the link port, so only one of them can be used
+  if link.is_some() && printer.is_some() {
+    eprintln!("{}", USAGE);
+    process::exit(1);
+  }
+
+  // Without a bootrom the game starts in the state the model's bootrom leaves behind
+  let (bootrom_name, gamerom_name) = match files.len() {
+    1 => (None, files.remove(0)),
+    2 => (Some(files.remove(0)), files.remove(0)),
+    _ => {
+      eprintln!("{}", USAGE);
+      process::exit(1);
+    }
+  };
+
+  let gamerom = read_binary(&gamerom_name);
+
+  let mut cartridge = Cartridge::new(gamerom, ClockSource::Host).unwrap_or_else(|error| {
+    eprintln!("{}: {}", gamerom_name, error);
+    process::exit(1);
+  });
+
+  if let Err(error) = cartridge.header().verify_global_checksum(cartridge.rom()) {
+    println!("Warning: {}", error);
+  }
+
+  let save_name = Path::new(&gamerom_name).with_extension("sav");
+
+  if let Err(error) = cartridge.attach_save_file(&save_name) {
+    eprintln!("{}: {}", save_name.display(), error);
+    process::exit(1);
+  }
+
+  print!("{:?}", cartridge.header());
+
+  let mut game_boy = match bootrom_name {
+    Some(bootrom_name) => GameBoy::new(model, renderer, read_binary(bootrom_name), cartridge),
+    None => GameBoy::without_bootrom(model, renderer, cartridge),
+  };
+
+  game_boy.set_access_blocking(access_blocking);
+
+  // Printouts are saved into the directory as they come out
+  if let Some(directory) = printer {
+    game_boy.connect_serial(Box::new(Printer::new(directory)));
+  }
+
+  // Addresses are a TCP host:port, or a Unix socket path prefixed with "unix:"
+  if let Some((listen, address)) = link {
+    if listen {
+      println!("Waiting for link cable on {}", address);
+    }
+
+    let cable = if listen { LinkCable::listen(&address) } else { LinkCable::connect(&address) };
+
+    match cable {
+      Ok(mut cable) => {
+        cable.set_stop_flag(&STOP);
+        game_boy.connect_serial(Box::new(cable));
+      }
+      Err(error) => {
+        eprintln!("{}: {}", address, error);
+        process::exit(1);
+      }
+    }
+  }
 
-  let bootrom = read_binary(bootrom_name);
-  let gamerom = read_binary(gamerom_name);
+  if let Err(error) = ctrlc::set_handler(|| STOP.store(true, Ordering::Relaxed)) {
+    eprintln!("Warning: {}", error);
+  }
 
-  let mut game_boy = game_boy::GameBoy::new(bootrom, gamerom);
+  game_boy.run(&STOP);
 
-  game_boy.run();
+  if let Err(error) = game_boy.flush() {
+    eprintln!("{}: {}", save_name.display(), error);
+  }
 }
 
 fn read_binary<P: AsRef<Path>>(path: P) -> Box<[u8]> {
@@ -0,0 +1,99 @@
+use super::super::display::OAM_SIZE;
+
+// Cycles between the write to 0xFF46 and the first byte being copied
+const START_DELAY: u8 = 2;
+
+// OAM DMA copies a page of memory into OAM, one byte per M-cycle
+#[derive(Default)]
+pub struct Dma {
+  source: u8, // The upper byte of the source address, as last written to 0xFF46
+  start_delay: u8,
+  base: u16,
+  offset: Option<u16>,
+}
+
+impl Dma {
+  pub fn with_source(source: u8) -> Dma {
+    Dma {
+      source,
+      ..Dma::default()
+    }
+  }
+
+  pub fn read(&self) -> u8 {
+    self.source
+  }
+
+  // Starting a new transfer lets any running one carry on until the new one takes over
+  pub fn write(&mut self, value: u8) {
+    self.source = value;
+    self.start_delay = START_DELAY;
+  }
+
+  // Whether the CPU is locked out of everything but high RAM and the I/O registers
+  pub fn active(&self) -> bool {
+    self.offset.is_some()
+  }
+
+  // Advances by one M-cycle, returning the source address and OAM offset of the byte to copy
+  pub fn step(&mut self) -> Option<(u16, u16)> {
+    if self.offset == Some(OAM_SIZE) {
+      self.offset = None;
+    }
+
+    if self.start_delay > 0 {
+      self.start_delay -= 1;
+
+      if self.start_delay == 0 {
+        self.base = (self.source as u16) << 8;
+        self.offset = Some(0);
+      }
+    }
+
+    let offset = self.offset?;
+
+    self.offset = Some(offset + 1);
+
+    Some((self.base + offset, offset))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn copies_a_page_after_the_start_delay() {
+    let mut dma = Dma::default();
+
+    dma.write(0xC1);
+    assert_eq!(dma.step(), None);
+    assert!(!dma.active());
+
+    for offset in 0..OAM_SIZE {
+      assert_eq!(dma.step(), Some((0xC100 + offset, offset)));
+      assert!(dma.active());
+    }
+
+    assert_eq!(dma.step(), None);
+    assert!(!dma.active());
+    assert_eq!(dma.read(), 0xC1);
+  }
+
+  #[test]
+  fn restarting_takes_over_after_the_delay() {
+    let mut dma = Dma::default();
+
+    dma.write(0xC1);
+
+    for _ in 0..11 {
+      dma.step();
+    }
+
+    // The old transfer carries on while the new one starts up
+    dma.write(0xD0);
+    assert_eq!(dma.step(), Some((0xC10A, 0x0A)));
+    assert_eq!(dma.step(), Some((0xD000, 0x00)));
+    assert_eq!(dma.read(), 0xD0);
+  }
+}
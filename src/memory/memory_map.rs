@@ -1,111 +1,462 @@
+use std::io;
+
 use super::Memory;
-use super::random_access_memory::RandomAccessMemory;
+use super::RandomAccessMemory;
+use super::dma::Dma;
+use super::super::processor::CYCLES_PER_MACHINE_CYCLE;
 
-// use super::super::io_ports::IOPorts;
+use super::super::interrupts::Interrupts;
+use super::super::cartridge::Cartridge;
+use super::super::model::Model;
+use super::super::display::{Display, Renderer, VRAM_SIZE, OAM_SIZE};
+use super::super::joypad::{Joypad, Button};
+use super::super::serial::{Serial, SerialDevice};
+use super::super::timer::Timer;
+use super::super::sound::Sound;
 
 const BOOTROM_START: u16 = 0x0000;
-const BOOTROM_SIZE: u16 = 0xFF;
+const BOOTROM_SIZE: u16 = 0x100;
 const BOOTROM_END: u16 = BOOTROM_START + BOOTROM_SIZE - 1;
 
+// The CGB bootrom is larger and leaves a gap for the cartridge header
+const CGB_BOOTROM_START: u16 = 0x0200;
+const CGB_BOOTROM_END: u16 = 0x08FF;
+
 const GAMEROM_START: u16 = 0x0000;
-const GAMEROM_SIZE: u16 = 0x4000;
+const GAMEROM_SIZE: u16 = 0x8000;
 const GAMEROM_END: u16 = GAMEROM_START + GAMEROM_SIZE - 1;
 
 const VRAM_START: u16 = 0x8000;
-const VRAM_SIZE: u16 = 0x2000;
 const VRAM_END: u16 = VRAM_START + VRAM_SIZE - 1;
 
+const EXTERNAL_RAM_START: u16 = 0xA000;
+const EXTERNAL_RAM_SIZE: u16 = 0x2000;
+const EXTERNAL_RAM_END: u16 = EXTERNAL_RAM_START + EXTERNAL_RAM_SIZE - 1;
+
 const RAM_START: u16 = 0xC000;
 pub const RAM_SIZE: u16 = 0x2000;
 const RAM_END: u16 = RAM_START + RAM_SIZE - 1;
 
+// The CGB switches the upper half of work RAM between seven banks
+const RAM_BANK_SIZE: u16 = 0x1000;
+const RAM_BANKS: u16 = 8;
+
+// Mirrors the start of work RAM, as the upper address line isn't decoded
+const ECHO_RAM_START: u16 = 0xE000;
+const ECHO_RAM_END: u16 = 0xFDFF;
+
+const OAM_START: u16 = 0xFE00;
+const OAM_END: u16 = OAM_START + OAM_SIZE - 1;
+
+const UNUSABLE_START: u16 = 0xFEA0;
+const UNUSABLE_END: u16 = 0xFEFF;
+
 const IO_REG_START: u16 = 0xFF00;
-const IO_REG_SIZE: u16 = 0x4c;
+const IO_REG_SIZE: u16 = 0x80;
 const IO_REG_END: u16 = IO_REG_START + IO_REG_SIZE - 1;
 
+// High RAM stops one byte short of the end, which is taken by IE
 const ZERO_PAGE_START: u16 = 0xFF80;
 const ZERO_PAGE_SIZE: u16 = 0x7F;
 const ZERO_PAGE_END: u16 = ZERO_PAGE_START + ZERO_PAGE_SIZE - 1;
 
 pub const IO_BASE_REG: u16 = 0xFF00;
-/* Save for later
-const IO_NR_11_REG: u16 = 0xFF11;
-const IO_NR_52_REG: u16 = 0xFF26;
-*/
+pub const JOYPAD_REG: u16 = 0xFF00;
+const SERIAL_REG_START: u16 = 0xFF01;
+const SERIAL_REG_END: u16 = 0xFF02;
+pub const DIVIDER_REG: u16 = 0xFF04;
+const TIMER_REG_START: u16 = 0xFF04;
+const TIMER_REG_END: u16 = 0xFF07;
+pub const INTERRUPT_FLAG_REG: u16 = 0xFF0F;
+const SOUND_REG_START: u16 = 0xFF10;
+const SOUND_REG_END: u16 = 0xFF3F;
+const DISPLAY_REG_START: u16 = 0xFF40;
+const DISPLAY_REG_END: u16 = 0xFF4B;
+const DMA_REG: u16 = 0xFF46;
+const BOOTROM_DISABLE_REG: u16 = 0xFF50;
+pub const INTERRUPT_ENABLE_REG: u16 = 0xFFFF;
+
+// CGB only
+const SPEED_REG: u16 = 0xFF4D; // KEY1
+const VRAM_BANK_REG: u16 = 0xFF4F; // VBK
+const INFRARED_REG: u16 = 0xFF56; // RP
+const COLOR_PALETTE_REG_START: u16 = 0xFF68; // BCPS/BCPD/OCPS/OCPD/OPRI
+const COLOR_PALETTE_REG_END: u16 = 0xFF6C;
+const RAM_BANK_REG: u16 = 0xFF70; // SVBK
+const UNDOCUMENTED_REG_START: u16 = 0xFF72;
+const UNDOCUMENTED_REG_END: u16 = 0xFF75;
+const PCM_REG_START: u16 = 0xFF76; // PCM12/PCM34
+const PCM_REG_END: u16 = 0xFF77;
+
+const SPEED_DOUBLE_FLAG: u8 = 0x80;
+const SPEED_SWITCH_ARMED_FLAG: u8 = 0x01;
+
+const INFRARED_WRITABLE: u8 = 0xC1; // Read enable and the LED
+const INFRARED_NO_LIGHT: u8 = 0x02; // Nothing is ever received
 
 enum AddressType {
   Bootrom(u16),
   Gamerom(u16),
+  ExternalRam(u16),
   ZeroPage(u16),
   Ram(u16),
   Vram(u16),
-  IoReg(u16),
-  // IoNr52Reg,
-  // IoNr11Reg,
+  Oam(u16),
+  Unusable(u16),
+  JoypadReg,
+  SerialReg(u16),
+  TimerReg(u16),
+  InterruptFlagReg,
+  SoundReg(u16),
+  DisplayReg(u16),
+  DmaReg,
+  SpeedReg,
+  BootromDisableReg,
+  InfraredReg,
+  RamBankReg,
+  UndocumentedReg(u16),
+  PcmReg,
+  UnmappedReg,
+  InterruptEnableReg,
 }
 
 pub struct MemoryMap {
+  model: Model,
   bootrom: Box<[u8]>,
-  gamerom: Box<[u8]>,
+  bootrom_mapped: bool, // Cleared for good by the bootrom's final write to 0xFF50
+  cartridge: Cartridge,
   zero_page: RandomAccessMemory,
   ram: RandomAccessMemory,
-  vram: RandomAccessMemory,
-  io: RandomAccessMemory,
-  // io: IOPorts,
+  ram_bank: u8,
+  display: Display,
+  joypad: Joypad,
+  serial: Serial,
+  timer: Timer,
+  sound: Sound,
+  interrupts: Interrupts,
+  dma: Dma,
+  access_blocking: bool, // Whether the PPU locks the CPU out of VRAM and OAM while using them
+  double_speed: bool,
+  speed_switch_armed: bool,
+  infrared: u8,
+  undocumented: [u8; 4],
 }
 
 impl MemoryMap {
-  pub fn new(bootrom: Box<[u8]>, gamerom: Box<[u8]>) -> MemoryMap {
+  pub fn new(model: Model, renderer: Renderer, bootrom: Box<[u8]>, cartridge: Cartridge) -> MemoryMap {
+    let color = model.is_color();
+    let ram_size = if color { RAM_BANK_SIZE * RAM_BANKS } else { RAM_SIZE };
+
     MemoryMap {
-      bootrom: bootrom,
-      gamerom: gamerom,
+      model,
+      bootrom,
+      bootrom_mapped: true,
+      cartridge,
       zero_page: RandomAccessMemory::new(ZERO_PAGE_SIZE as usize),
-      ram: RandomAccessMemory::new(RAM_SIZE as usize),
-      vram: RandomAccessMemory::new(VRAM_SIZE as usize),
-      io: RandomAccessMemory::new(IO_REG_SIZE as usize),
-      // io: IOPorts::default(),
+      ram: RandomAccessMemory::new(ram_size as usize),
+      ram_bank: 0,
+      display: Display::new(color, renderer),
+      joypad: Joypad::default(),
+      serial: Serial::new(color),
+      timer: Timer::default(),
+      sound: Sound::new(),
+      interrupts: Interrupts::default(),
+      dma: Dma::default(),
+      access_blocking: true,
+      double_speed: false,
+      speed_switch_armed: false,
+      infrared: 0,
+      undocumented: [0; 4],
+    }
+  }
+
+  // Puts the I/O registers in the state the model's bootrom leaves them in
+  pub fn skip_bootrom(&mut self) {
+    self.bootrom_mapped = false;
+    self.timer.set_divider(self.model.post_boot_divider());
+    self.dma = Dma::with_source(self.model.post_boot_dma_source());
+
+    for (address, value) in self.model.post_boot_io() {
+      self.write_byte(address, value);
+    }
+  }
+
+  pub fn rumble(&self) -> bool {
+    self.cartridge.rumble()
+  }
+
+  pub fn flush_cartridge(&mut self) -> io::Result<()> {
+    self.cartridge.flush()
+  }
+
+  // Lets the CPU access VRAM and OAM at any time, which is handy when debugging
+  pub fn set_access_blocking(&mut self, enabled: bool) {
+    self.access_blocking = enabled;
+  }
+
+  pub fn press(&mut self, button: Button) {
+    self.joypad.press(button, &mut self.interrupts);
+  }
+
+  pub fn release(&mut self, button: Button) {
+    self.joypad.release(button);
+  }
+
+  pub fn connect_serial(&mut self, device: Box<dyn SerialDevice>) {
+    self.serial.connect(device);
+  }
+
+  pub fn serial_output(&self) -> &[u8] {
+    self.serial.output()
+  }
+
+  pub fn take_frame(&mut self) -> Option<&[u8]> {
+    self.display.take_frame()
+  }
+
+  // Reads from 0xFEA0-0xFEFF depend on the model, as nothing is connected there.
+  // The DMG family reads 0xFF while OAM is locked, like OAM itself
+  fn read_unusable(&self, address: u16) -> u8 {
+    match self.model {
+      _ if !self.oam_accessible() && !self.model.is_color() => 0xFF,
+      Model::Cgb => {
+        let nibble = (address as u8) & 0xF0;
+
+        nibble | nibble >> 4
+      }
+      _ => 0x00,
     }
   }
 
+  // Bank 0 is always at 0xC000, while SVBK picks the one at 0xD000 (bank 0 selects bank 1)
+  fn ram_offset(&self, offset: u16) -> u16 {
+    if offset < RAM_BANK_SIZE {
+      offset
+    } else {
+      (self.ram_bank.max(1) as u16) * RAM_BANK_SIZE + offset - RAM_BANK_SIZE
+    }
+  }
+
+  fn tick_dma(&mut self, cycles: u32) {
+    for _ in 0..cycles / CYCLES_PER_MACHINE_CYCLE {
+      if let Some((source, offset)) = self.dma.step() {
+        // DMA sees the echo of work RAM all the way up to the end of the address space
+        let source = if source >= ECHO_RAM_START { source - (ECHO_RAM_START - RAM_START) } else { source };
+        let value = self.read_bus(source);
+
+        self.display.write_oam(offset, value);
+      }
+    }
+  }
+
+  fn vram_accessible(&self) -> bool {
+    !self.access_blocking || self.display.vram_accessible()
+  }
+
+  fn oam_accessible(&self) -> bool {
+    !self.access_blocking || self.display.oam_accessible()
+  }
+
+  // While OAM DMA runs, the CPU only reaches the I/O registers, high RAM and IE
+  fn dma_blocks(&self, address: u16) -> bool {
+    self.dma.active() && address < IO_REG_START
+  }
+
+  fn read_speed(&self) -> u8 {
+    let mut value = 0x7E;
+
+    if self.double_speed { value |= SPEED_DOUBLE_FLAG; }
+    if self.speed_switch_armed { value |= SPEED_SWITCH_ARMED_FLAG; }
+
+    value
+  }
+
+  // Every address is covered, overlays and single registers coming before the ranges they sit in
+  #[allow(clippy::match_overlapping_arm)]
   fn map_address(&self, address: u16) -> AddressType {
+    let color = self.model.is_color();
+
     match address {
-      BOOTROM_START ... BOOTROM_END => {
+      BOOTROM_START ..= BOOTROM_END if self.bootrom_mapped => {
+        AddressType::Bootrom(address - BOOTROM_START)
+      }
+
+      CGB_BOOTROM_START ..= CGB_BOOTROM_END if self.bootrom_mapped && self.bootrom.len() > address as usize => {
         AddressType::Bootrom(address - BOOTROM_START)
       }
 
-      GAMEROM_START ... GAMEROM_END => {
+      GAMEROM_START ..= GAMEROM_END => {
         AddressType::Gamerom(address - GAMEROM_START)
       }
 
-      RAM_START ... RAM_END => {
+      VRAM_START ..= VRAM_END => {
+        AddressType::Vram(address - VRAM_START)
+      }
+
+      EXTERNAL_RAM_START ..= EXTERNAL_RAM_END => {
+        AddressType::ExternalRam(address - EXTERNAL_RAM_START)
+      }
+
+      RAM_START ..= RAM_END => {
         AddressType::Ram(address - RAM_START)
       }
 
-      VRAM_START ... VRAM_END => {
-        AddressType::Vram(address - VRAM_START)
+      ECHO_RAM_START ..= ECHO_RAM_END => {
+        AddressType::Ram(address - ECHO_RAM_START)
+      }
+
+      OAM_START ..= OAM_END => {
+        AddressType::Oam(address - OAM_START)
+      }
+
+      UNUSABLE_START ..= UNUSABLE_END => {
+        AddressType::Unusable(address)
+      }
+
+      JOYPAD_REG => {
+        AddressType::JoypadReg
+      }
+
+      SERIAL_REG_START ..= SERIAL_REG_END => {
+        AddressType::SerialReg(address)
+      }
+
+      TIMER_REG_START ..= TIMER_REG_END => {
+        AddressType::TimerReg(address)
+      }
+
+      INTERRUPT_FLAG_REG => {
+        AddressType::InterruptFlagReg
+      }
+
+      SOUND_REG_START ..= SOUND_REG_END => {
+        AddressType::SoundReg(address)
+      }
+
+      DMA_REG => {
+        AddressType::DmaReg
+      }
+
+      DISPLAY_REG_START ..= DISPLAY_REG_END => {
+        AddressType::DisplayReg(address)
+      }
+
+      SPEED_REG if color => {
+        AddressType::SpeedReg
+      }
+
+      VRAM_BANK_REG if color => {
+        AddressType::DisplayReg(address)
+      }
+
+      BOOTROM_DISABLE_REG => {
+        AddressType::BootromDisableReg
       }
 
-      ZERO_PAGE_START ... ZERO_PAGE_END => {
+      INFRARED_REG if color => {
+        AddressType::InfraredReg
+      }
+
+      COLOR_PALETTE_REG_START ..= COLOR_PALETTE_REG_END if color => {
+        AddressType::DisplayReg(address)
+      }
+
+      RAM_BANK_REG if color => {
+        AddressType::RamBankReg
+      }
+
+      UNDOCUMENTED_REG_START ..= UNDOCUMENTED_REG_END if color => {
+        AddressType::UndocumentedReg(address - UNDOCUMENTED_REG_START)
+      }
+
+      PCM_REG_START ..= PCM_REG_END if color => {
+        AddressType::PcmReg
+      }
+
+      // Includes the CGB's HDMA registers, which read back as 0xFF while no transfer is running
+      IO_REG_START ..= IO_REG_END => {
+        AddressType::UnmappedReg
+      }
+
+      ZERO_PAGE_START ..= ZERO_PAGE_END => {
         AddressType::ZeroPage(address - ZERO_PAGE_START)
       }
 
-      IO_REG_START ... IO_REG_END => {
-        AddressType::IoReg(address - IO_REG_START)
+      INTERRUPT_ENABLE_REG => {
+        AddressType::InterruptEnableReg
       }
+    }
+  }
+}
+
+impl MemoryMap {
+  fn read_bus(&self, address: u16) -> u8 {
+    match self.map_address(address) {
+      AddressType::Bootrom(offset) => self.bootrom[offset as usize],
+      AddressType::Gamerom(offset) => self.cartridge.read_rom(offset),
+      AddressType::ExternalRam(offset) => self.cartridge.read_ram(offset),
+      AddressType::ZeroPage(offset) => self.zero_page.read_byte(offset),
+      AddressType::Ram(offset) => self.ram.read_byte(self.ram_offset(offset)),
+      AddressType::Vram(_) if !self.vram_accessible() => 0xFF,
+      AddressType::Vram(offset) => self.display.read_vram(offset),
+      AddressType::Oam(_) if !self.oam_accessible() => 0xFF,
+      AddressType::Oam(offset) => self.display.read_oam(offset),
+      AddressType::Unusable(address) => self.read_unusable(address),
+      AddressType::JoypadReg => self.joypad.read(),
+      AddressType::SerialReg(register) => self.serial.read_register(register),
+      AddressType::TimerReg(register) => self.timer.read_register(register),
+      AddressType::InterruptFlagReg => self.interrupts.read_flag(),
+      AddressType::SoundReg(register) => self.sound.read_register(register),
+      AddressType::DisplayReg(register) => self.display.read_register(register),
+      AddressType::DmaReg => self.dma.read(),
+      AddressType::SpeedReg => self.read_speed(),
+      AddressType::BootromDisableReg => if self.bootrom_mapped { 0xFE } else { 0xFF },
+      AddressType::InfraredReg => self.infrared | INFRARED_NO_LIGHT | !(INFRARED_WRITABLE | INFRARED_NO_LIGHT),
+      AddressType::RamBankReg => 0xF8 | self.ram_bank,
+      AddressType::UndocumentedReg(3) => self.undocumented[3] | 0x8F, // Only bits 4-6 are implemented
+      AddressType::UndocumentedReg(index) => self.undocumented[index as usize],
+      AddressType::PcmReg => 0x00, // Every channel is silent
+      AddressType::UnmappedReg => 0xFF,
+      AddressType::InterruptEnableReg => self.interrupts.read_enable(),
+    }
+  }
 
-      // IO_NR_11_REG => {
-      //   AddressType::IoNr11Reg
-      // }
-      //
-      // IO_NR_52_REG => {
-      //   AddressType::IoNr52Reg
-      // }
+  fn write_bus(&mut self, address: u16, value: u8) {
+    match self.map_address(address) {
+      // Writes to the ROM area go to the cartridge's bank controller, even while the bootrom is mapped
+      AddressType::Bootrom(_) | AddressType::Gamerom(_) => self.cartridge.write_rom(address, value),
+      AddressType::ExternalRam(offset) => self.cartridge.write_ram(offset, value),
+      AddressType::ZeroPage(offset) => self.zero_page.write_byte(offset, value),
+      AddressType::Ram(offset) => {
+        let offset = self.ram_offset(offset);
 
-      _ => {
-        panic!("Unrecognized address: {:#06x}", address);
+        self.ram.write_byte(offset, value);
       }
+      // Writes the PPU locks out are dropped
+      AddressType::Vram(_) if !self.vram_accessible() => {}
+      AddressType::Vram(offset) => self.display.write_vram(offset, value),
+      AddressType::Oam(_) if !self.oam_accessible() => {}
+      AddressType::Oam(offset) => self.display.write_oam(offset, value),
+      AddressType::Unusable(_) => {}
+      AddressType::JoypadReg => self.joypad.write(value, &mut self.interrupts),
+      AddressType::SerialReg(register) => self.serial.write_register(register, value),
+      AddressType::TimerReg(register) => self.timer.write_register(register, value),
+      AddressType::InterruptFlagReg => self.interrupts.write_flag(value),
+      AddressType::SoundReg(register) => self.sound.write_register(register, value),
+      AddressType::DisplayReg(register) => self.display.write_register(register, value),
+      AddressType::DmaReg => self.dma.write(value),
+      AddressType::SpeedReg => self.speed_switch_armed = (value & SPEED_SWITCH_ARMED_FLAG) != 0,
+      // Once unmapped the bootrom can't be brought back without a reset
+      AddressType::BootromDisableReg => if value & 0x01 != 0 { self.bootrom_mapped = false },
+      AddressType::InfraredReg => self.infrared = value & INFRARED_WRITABLE,
+      AddressType::RamBankReg => self.ram_bank = value & 0x07,
+      AddressType::UndocumentedReg(3) => self.undocumented[3] = value & 0x70,
+      AddressType::UndocumentedReg(index) => self.undocumented[index as usize] = value,
+      AddressType::PcmReg | AddressType::UnmappedReg => {}
+      AddressType::InterruptEnableReg => self.interrupts.write_enable(value),
     }
   }
+
 }
 
 impl Memory for MemoryMap {
@@ -113,28 +464,340 @@ impl Memory for MemoryMap {
   type W = u16;
 
   fn read_byte(&self, address: u16) -> u8 {
-    match self.map_address(address) {
-      AddressType::Bootrom(offset) => self.bootrom[offset as usize],
-      AddressType::Gamerom(offset) => self.gamerom[offset as usize],
-      AddressType::ZeroPage(offset) => self.zero_page.read_byte(offset),
-      AddressType::Ram(offset) => self.ram.read_byte(offset),
-      AddressType::Vram(offset) => self.vram.read_byte(offset),
-      AddressType::IoReg(offset) => self.io.read_byte(offset),
-      // AddressType::IoNr11Reg => self.io.read_nr_11(),
-      // AddressType::IoNr52Reg => self.io.read_nr_52(),
+    if self.dma_blocks(address) {
+      return 0xFF;
     }
+
+    self.read_bus(address)
   }
 
   fn write_byte(&mut self, address: u16, value: u8) {
-    match self.map_address(address) {
-      AddressType::Bootrom(_) => panic!("Cannot write to read-only memory."),
-      AddressType::Gamerom(_) => panic!("Cannot write to read-only memory."),
-      AddressType::ZeroPage(offset) => self.zero_page.write_byte(offset, value),
-      AddressType::Ram(offset) => self.ram.write_byte(offset, value),
-      AddressType::Vram(offset) => self.vram.write_byte(offset, value),
-      AddressType::IoReg(offset) => self.io.write_byte(offset, value),
-      // AddressType::IoNr11Reg => self.io.write_nr_11(value),
-      // AddressType::IoNr52Reg => self.io.write_nr_52(value),
+    if !self.dma_blocks(address) {
+      self.write_bus(address, value);
+    }
+  }
+
+  fn tick(&mut self, cycles: u32) {
+    self.tick_dma(cycles);
+
+    // The PPU and the cartridge's clock keep their own pace when the CPU runs at double speed
+    let normal_cycles = if self.double_speed { cycles / 2 } else { cycles };
+
+    self.display.tick(normal_cycles, &mut self.interrupts);
+    self.timer.tick(cycles, &mut self.interrupts);
+    self.serial.tick(cycles, &mut self.interrupts);
+    self.cartridge.tick(normal_cycles);
+  }
+
+  fn switch_speed(&mut self) -> bool {
+    if !self.speed_switch_armed {
+      return false;
+    }
+
+    self.double_speed = !self.double_speed;
+    self.speed_switch_armed = false;
+
+    true
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use super::super::super::cartridge::{self, ClockSource};
+
+  // With the LCD off and a blank bootrom still mapped
+  fn memory_map(model: Model) -> MemoryMap {
+    with_bootrom(model, vec![0; BOOTROM_SIZE as usize])
+  }
+
+  // The cartridge's ROM holds 0xCA outside of its header
+  fn with_bootrom(model: Model, bootrom: Vec<u8>) -> MemoryMap {
+    let mut rom = cartridge::test_rom(0x00, 0x00, 0x00);
+
+    for (address, byte) in rom.iter_mut().enumerate() {
+      if !(0x0100..0x0150).contains(&address) {
+        *byte = 0xCA;
+      }
+    }
+
+    let cartridge = Cartridge::new(rom.into_boxed_slice(), ClockSource::Emulated).unwrap();
+
+    MemoryMap::new(model, Renderer::Scanline, bootrom.into_boxed_slice(), cartridge)
+  }
+
+  fn machine_cycles(memory_map: &mut MemoryMap, count: u32) {
+    memory_map.tick(count * CYCLES_PER_MACHINE_CYCLE);
+  }
+
+  #[test]
+  fn dma_locks_out_everything_below_the_io_registers() {
+    let mut memory_map = memory_map(Model::Dmg);
+
+    for offset in 0..OAM_SIZE {
+      memory_map.write_byte(RAM_START + offset, offset as u8);
+    }
+
+    memory_map.write_byte(ZERO_PAGE_START, 0x12);
+    memory_map.write_byte(DMA_REG, 0xC0);
+
+    machine_cycles(&mut memory_map, 1);
+    assert_eq!(memory_map.read_byte(RAM_START), 0x00);
+
+    machine_cycles(&mut memory_map, 1);
+    assert_eq!(memory_map.read_byte(RAM_START + 1), 0xFF);
+    assert_eq!(memory_map.read_byte(OAM_START), 0xFF);
+    assert_eq!(memory_map.read_byte(ZERO_PAGE_START), 0x12);
+    assert_eq!(memory_map.read_byte(DMA_REG), 0xC0);
+
+    // Writes are dropped too
+    memory_map.write_byte(RAM_START, 0x99);
+
+    machine_cycles(&mut memory_map, OAM_SIZE as u32 - 1);
+    assert_eq!(memory_map.read_byte(RAM_START), 0xFF);
+
+    machine_cycles(&mut memory_map, 1);
+    assert_eq!(memory_map.read_byte(RAM_START), 0x00);
+
+    for offset in 0..OAM_SIZE {
+      assert_eq!(memory_map.read_byte(OAM_START + offset), offset as u8);
+    }
+  }
+
+  #[test]
+  fn dma_from_the_top_pages_reads_work_ram() {
+    for &(source, ram) in &[(0xFE, 0xDE00), (0xFF, 0xDF00)] {
+      let mut memory_map = memory_map(Model::Dmg);
+
+      for offset in 0..OAM_SIZE {
+        memory_map.write_byte(ram + offset, !offset as u8);
+      }
+
+      memory_map.write_byte(DMA_REG, source);
+      machine_cycles(&mut memory_map, OAM_SIZE as u32 + 2);
+
+      for offset in 0..OAM_SIZE {
+        assert_eq!(memory_map.read_byte(OAM_START + offset), !offset as u8, "{:#04x}", source);
+      }
+    }
+  }
+
+  #[test]
+  fn restarting_dma_starts_over_from_the_new_source() {
+    let mut memory_map = memory_map(Model::Dmg);
+
+    for offset in 0..OAM_SIZE {
+      memory_map.write_byte(RAM_START + offset, 0x11);
+      memory_map.write_byte(RAM_START + 0x100 + offset, 0x22);
+    }
+
+    memory_map.write_byte(DMA_REG, 0xC0);
+    machine_cycles(&mut memory_map, 50);
+    memory_map.write_byte(DMA_REG, 0xC1);
+    machine_cycles(&mut memory_map, OAM_SIZE as u32 + 2);
+
+    for offset in 0..OAM_SIZE {
+      assert_eq!(memory_map.read_byte(OAM_START + offset), 0x22);
+    }
+  }
+
+  // Switches the LCD on, which starts the first line's OAM scan straight away
+  fn enable_lcd(memory_map: &mut MemoryMap) {
+    memory_map.write_byte(DISPLAY_REG_START, 0x80);
+  }
+
+  #[test]
+  fn the_ppu_locks_out_vram_and_oam() {
+    let mut memory_map = memory_map(Model::Dmg);
+
+    memory_map.write_byte(VRAM_START, 0x11);
+    memory_map.write_byte(OAM_START, 0x22);
+    enable_lcd(&mut memory_map);
+
+    // Mode 2 only takes OAM, and the unusable area after it on the DMG
+    memory_map.write_byte(OAM_START, 0x33);
+    assert_eq!(memory_map.read_byte(OAM_START), 0xFF);
+    assert_eq!(memory_map.read_byte(UNUSABLE_START), 0xFF);
+    assert_eq!(memory_map.read_byte(VRAM_START), 0x11);
+
+    // Mode 3 takes both
+    machine_cycles(&mut memory_map, 20);
+    memory_map.write_byte(VRAM_START, 0x44);
+    memory_map.write_byte(OAM_START, 0x55);
+    assert_eq!(memory_map.read_byte(VRAM_START), 0xFF);
+    assert_eq!(memory_map.read_byte(OAM_START), 0xFF);
+
+    // HBlank gives them back, without any of the writes made meanwhile
+    machine_cycles(&mut memory_map, 43);
+    assert_eq!(memory_map.read_byte(VRAM_START), 0x11);
+    assert_eq!(memory_map.read_byte(OAM_START), 0x22);
+    assert_eq!(memory_map.read_byte(UNUSABLE_START), 0x00);
+  }
+
+  #[test]
+  fn access_blocking_can_be_turned_off() {
+    let mut memory_map = memory_map(Model::Dmg);
+
+    memory_map.set_access_blocking(false);
+    enable_lcd(&mut memory_map);
+    machine_cycles(&mut memory_map, 20);
+
+    memory_map.write_byte(VRAM_START, 0x44);
+    memory_map.write_byte(OAM_START, 0x55);
+    assert_eq!(memory_map.read_byte(VRAM_START), 0x44);
+    assert_eq!(memory_map.read_byte(OAM_START), 0x55);
+  }
+
+  #[test]
+  fn disabling_the_bootrom_uncovers_the_cartridge() {
+    let mut memory_map = with_bootrom(Model::Dmg, vec![0xB0; BOOTROM_SIZE as usize]);
+
+    assert_eq!(memory_map.read_byte(0x0000), 0xB0);
+    assert_eq!(memory_map.read_byte(BOOTROM_END), 0xB0);
+    assert_eq!(memory_map.read_byte(0x0104), 0xCE);
+    assert_eq!(memory_map.read_byte(BOOTROM_DISABLE_REG), 0xFE);
+
+    // Only bit 0 counts
+    memory_map.write_byte(BOOTROM_DISABLE_REG, 0xFE);
+    assert_eq!(memory_map.read_byte(0x0000), 0xB0);
+
+    memory_map.write_byte(BOOTROM_DISABLE_REG, 0x01);
+    assert_eq!(memory_map.read_byte(0x0000), 0xCA);
+    assert_eq!(memory_map.read_byte(BOOTROM_END), 0xCA);
+    assert_eq!(memory_map.read_byte(BOOTROM_DISABLE_REG), 0xFF);
+
+    // And it can't be mapped back in
+    memory_map.write_byte(BOOTROM_DISABLE_REG, 0x00);
+    assert_eq!(memory_map.read_byte(0x0000), 0xCA);
+    assert_eq!(memory_map.read_byte(BOOTROM_DISABLE_REG), 0xFF);
+  }
+
+  #[test]
+  fn cgb_bootrom_leaves_a_gap_for_the_header() {
+    let mut memory_map = with_bootrom(Model::Cgb, vec![0xB0; CGB_BOOTROM_END as usize + 1]);
+
+    assert_eq!(memory_map.read_byte(0x0000), 0xB0);
+    assert_eq!(memory_map.read_byte(0x0104), 0xCE);
+    assert_eq!(memory_map.read_byte(0x01FF), 0xCA);
+    assert_eq!(memory_map.read_byte(CGB_BOOTROM_START), 0xB0);
+    assert_eq!(memory_map.read_byte(CGB_BOOTROM_END), 0xB0);
+    assert_eq!(memory_map.read_byte(CGB_BOOTROM_END + 1), 0xCA);
+
+    memory_map.write_byte(BOOTROM_DISABLE_REG, 0x01);
+    assert_eq!(memory_map.read_byte(0x0000), 0xCA);
+    assert_eq!(memory_map.read_byte(CGB_BOOTROM_START), 0xCA);
+    assert_eq!(memory_map.read_byte(CGB_BOOTROM_END), 0xCA);
+  }
+
+  #[test]
+  fn skipping_the_bootrom_sets_up_the_io_registers() {
+    // Model, DIV, SC, DMA
+    let expected = [
+      (Model::Dmg0, 0x18, 0x7E, 0xFF),
+      (Model::Dmg, 0xAB, 0x7E, 0xFF),
+      (Model::Mgb, 0xAB, 0x7E, 0xFF),
+      (Model::Sgb, 0x00, 0x7E, 0xFF),
+      (Model::Sgb2, 0x00, 0x7E, 0xFF),
+      (Model::Cgb, 0x1E, 0x7F, 0x00),
+      (Model::Agb, 0x1E, 0x7F, 0x00),
+    ];
+
+    for &(model, divider, serial_control, dma) in &expected {
+      let mut memory_map = memory_map(model);
+
+      memory_map.skip_bootrom();
+
+      assert_eq!(memory_map.read_byte(DIVIDER_REG), divider, "{:?}", model);
+      // Sound is left powered on, though no channel is playing
+      assert_eq!(memory_map.read_byte(0xFF26), 0xF0, "{:?}", model);
+      assert_eq!(memory_map.read_byte(SERIAL_REG_END), serial_control, "{:?}", model);
+      assert_eq!(memory_map.read_byte(DMA_REG), dma, "{:?}", model);
+      assert_eq!(memory_map.read_byte(DISPLAY_REG_START), 0x91, "{:?}", model);
+      assert_eq!(memory_map.read_byte(0xFF47), 0xFC, "{:?}", model);
+      assert_eq!(memory_map.read_byte(INTERRUPT_FLAG_REG), 0xE1, "{:?}", model);
+      assert_eq!(memory_map.read_byte(BOOTROM_DISABLE_REG), 0xFF, "{:?}", model);
+      assert_eq!(memory_map.read_byte(0x0000), 0xCA, "{:?}", model);
     }
   }
+
+  #[test]
+  fn addresses_route_to_the_right_place() {
+    // Model, address, value written first if any, value read back
+    let expected: &[(Model, u16, Option<u8>, u8)] = &[
+      // High RAM stops short of IE, which keeps all eight bits
+      (Model::Dmg, ZERO_PAGE_START, Some(0x11), 0x11),
+      (Model::Dmg, ZERO_PAGE_END, Some(0x22), 0x22),
+      (Model::Dmg, INTERRUPT_ENABLE_REG, Some(0xE5), 0xE5),
+
+      // Nothing is connected to the unusable area, and what that reads as varies
+      (Model::Dmg, UNUSABLE_START, Some(0x12), 0x00),
+      (Model::Mgb, UNUSABLE_END, None, 0x00),
+      (Model::Cgb, 0xFEA5, Some(0x12), 0xAA),
+      (Model::Cgb, UNUSABLE_END, None, 0xFF),
+      (Model::Agb, 0xFEA5, None, 0x00),
+
+      // Unimplemented I/O bits read as 1
+      (Model::Dmg, 0xFF03, Some(0x00), 0xFF),
+      (Model::Dmg, TIMER_REG_END, Some(0x00), 0xF8),
+      (Model::Dmg, INTERRUPT_FLAG_REG, Some(0x00), 0xE0),
+      (Model::Dmg, SOUND_REG_START, Some(0x00), 0x80),
+      (Model::Dmg, 0xFF26, Some(0x00), 0x70),
+      (Model::Dmg, 0xFF26, Some(0x80), 0xF0),
+      (Model::Dmg, 0xFF27, Some(0x00), 0xFF),
+      (Model::Dmg, DISPLAY_REG_START + 1, Some(0x00), 0x84), // LY and LYC are both 0
+
+      // CGB registers are unmapped on the DMG
+      (Model::Dmg, SPEED_REG, Some(0x01), 0xFF),
+      (Model::Dmg, VRAM_BANK_REG, Some(0x01), 0xFF),
+      (Model::Dmg, COLOR_PALETTE_REG_START, Some(0x00), 0xFF),
+      (Model::Dmg, RAM_BANK_REG, Some(0x00), 0xFF),
+      (Model::Cgb, SPEED_REG, Some(0x00), 0x7E),
+      (Model::Cgb, VRAM_BANK_REG, Some(0x00), 0xFE),
+      (Model::Cgb, COLOR_PALETTE_REG_END, Some(0x00), 0xFE),
+      (Model::Cgb, RAM_BANK_REG, Some(0x02), 0xFA),
+      (Model::Cgb, UNDOCUMENTED_REG_START + 3, Some(0x00), 0x8F),
+      (Model::Cgb, PCM_REG_START, None, 0x00),
+      (Model::Cgb, 0xFF4C, Some(0x00), 0xFF),
+      (Model::Cgb, IO_REG_END, Some(0x00), 0xFF),
+    ];
+
+    for &(model, address, written, read) in expected {
+      let mut memory_map = memory_map(model);
+
+      if let Some(value) = written {
+        memory_map.write_byte(address, value);
+      }
+
+      assert_eq!(memory_map.read_byte(address), read, "{:?} {:#06x}", model, address);
+    }
+
+    // Everything after the DMG's display registers reads as 1, except the bootrom's
+    let mut dmg = memory_map(Model::Dmg);
+
+    for address in (DISPLAY_REG_END + 1..=IO_REG_END).filter(|&address| address != BOOTROM_DISABLE_REG) {
+      dmg.write_byte(address, 0x00);
+      assert_eq!(dmg.read_byte(address), 0xFF, "{:#06x}", address);
+    }
+  }
+
+  #[test]
+  fn echo_ram_mirrors_work_ram() {
+    let mut dmg = memory_map(Model::Dmg);
+
+    dmg.write_byte(RAM_START + 0x0123, 0x5A);
+    assert_eq!(dmg.read_byte(ECHO_RAM_START + 0x0123), 0x5A);
+
+    dmg.write_byte(ECHO_RAM_END, 0xA5);
+    assert_eq!(dmg.read_byte(ECHO_RAM_END - (ECHO_RAM_START - RAM_START)), 0xA5);
+
+    // On the CGB that includes the banked half
+    let mut cgb = memory_map(Model::Cgb);
+
+    cgb.write_byte(RAM_BANK_REG, 0x03);
+    cgb.write_byte(0xD000, 0x33);
+    assert_eq!(cgb.read_byte(0xF000), 0x33);
+
+    cgb.write_byte(RAM_BANK_REG, 0x04);
+    assert_eq!(cgb.read_byte(0xF000), 0x00);
+  }
 }
@@ -1,11 +1,12 @@
 mod random_access_memory;
 mod memory_map;
+mod dma;
 
 use std::ops;
 
-pub use self::random_access_memory::RandomAccessMemory;
 pub use self::memory_map::MemoryMap;
-pub use self::memory_map::IO_BASE_REG;
+pub use self::random_access_memory::RandomAccessMemory;
+pub use self::memory_map::{IO_BASE_REG, JOYPAD_REG, DIVIDER_REG, INTERRUPT_FLAG_REG, INTERRUPT_ENABLE_REG};
 
 pub trait Memory {
   type B: From<Self::W> + From<u16>;
@@ -22,4 +23,10 @@ pub trait Memory {
     self.write_byte(Self::B::from(address), (value & 0xFF) as u8);
     self.write_byte(Self::B::from(address + Self::W::from(1)), ((value >> 8) & 0xFF) as u8);
   }
+
+  // Advances anything attached to this memory by the given number of T-cycles
+  fn tick(&mut self, _cycles: u32) {}
+
+  // Performs a CGB speed switch if one has been armed through KEY1
+  fn switch_speed(&mut self) -> bool { false }
 }
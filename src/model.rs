@@ -0,0 +1,131 @@
+// The Game Boy hardware revisions, which mostly differ in the state their bootroms leave behind
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Model {
+  Dmg0, // Early original Game Boy
+  Dmg,
+  Mgb, // Game Boy Pocket
+  Sgb, // Super Game Boy
+  Sgb2,
+  Cgb, // Game Boy Color
+  Agb, // Game Boy Advance
+}
+
+const MODELS: [(&str, Model); 7] = [
+  ("dmg0", Model::Dmg0),
+  ("dmg", Model::Dmg),
+  ("mgb", Model::Mgb),
+  ("sgb", Model::Sgb),
+  ("sgb2", Model::Sgb2),
+  ("cgb", Model::Cgb),
+  ("agb", Model::Agb),
+];
+
+// CPU registers once the bootrom has handed over to the cartridge at 0x0100
+pub struct PostBootRegisters {
+  pub af: u16,
+  pub bc: u16,
+  pub de: u16,
+  pub hl: u16,
+  pub sp: u16,
+  pub pc: u16,
+}
+
+// I/O registers the bootroms of every model leave in the same state
+const POST_BOOT_IO: [(u16, u8); 36] = [
+  (0xFF00, 0xCF), // P1
+  (0xFF01, 0x00), // SB
+  (0xFF05, 0x00), // TIMA
+  (0xFF06, 0x00), // TMA
+  (0xFF07, 0xF8), // TAC
+  (0xFF0F, 0xE1), // IF
+  (0xFF10, 0x80), // NR10
+  (0xFF11, 0xBF), // NR11
+  (0xFF12, 0xF3), // NR12
+  (0xFF13, 0xFF), // NR13
+  (0xFF14, 0xBF), // NR14
+  (0xFF16, 0x3F), // NR21
+  (0xFF17, 0x00), // NR22
+  (0xFF18, 0xFF), // NR23
+  (0xFF19, 0xBF), // NR24
+  (0xFF1A, 0x7F), // NR30
+  (0xFF1B, 0xFF), // NR31
+  (0xFF1C, 0x9F), // NR32
+  (0xFF1D, 0xFF), // NR33
+  (0xFF1E, 0xBF), // NR34
+  (0xFF20, 0xFF), // NR41
+  (0xFF21, 0x00), // NR42
+  (0xFF22, 0x00), // NR43
+  (0xFF23, 0xBF), // NR44
+  (0xFF24, 0x77), // NR50
+  (0xFF25, 0xF3), // NR51
+  (0xFF40, 0x91), // LCDC
+  (0xFF41, 0x85), // STAT
+  (0xFF42, 0x00), // SCY
+  (0xFF43, 0x00), // SCX
+  (0xFF45, 0x00), // LYC
+  (0xFF47, 0xFC), // BGP
+  (0xFF48, 0xFF), // OBP0
+  (0xFF49, 0xFF), // OBP1
+  (0xFF4A, 0x00), // WY
+  (0xFF4B, 0x00), // WX
+];
+
+impl Model {
+  pub fn from_name(name: &str) -> Option<Model> {
+    MODELS.iter().find(|&&(model_name, _)| model_name == name).map(|&(_, model)| model)
+  }
+
+  pub fn is_color(self) -> bool {
+    self == Model::Cgb || self == Model::Agb
+  }
+
+  // The DMG and MGB bootroms leave H and C set unless the header checksum is zero
+  pub fn post_boot_registers(self, header_checksum: u8) -> PostBootRegisters {
+    let flags = if header_checksum == 0 { 0x80 } else { 0xB0 };
+
+    let (af, bc, de, hl) = match self {
+      Model::Dmg0 => (0x0100, 0xFF13, 0x00C1, 0x8403),
+      Model::Dmg => (0x0100 | flags, 0x0013, 0x00D8, 0x014D),
+      Model::Mgb => (0xFF00 | flags, 0x0013, 0x00D8, 0x014D),
+      Model::Sgb => (0x0100, 0x0014, 0x0000, 0xC060),
+      Model::Sgb2 => (0xFF00, 0x0014, 0x0000, 0xC060),
+      Model::Cgb => (0x1180, 0x0000, 0xFF56, 0x000D),
+      Model::Agb => (0x1100, 0x0100, 0xFF56, 0x000D),
+    };
+
+    PostBootRegisters {
+      af,
+      bc,
+      de,
+      hl,
+      sp: 0xFFFE,
+      pc: 0x0100,
+    }
+  }
+
+  pub fn post_boot_io(self) -> Vec<(u16, u8)> {
+    // NR52 goes first, as the other sound registers ignore writes while powered off
+    let mut io = vec![(0xFF26, match self { Model::Sgb | Model::Sgb2 => 0xF0, _ => 0xF1 })]; // NR52
+
+    io.extend_from_slice(&POST_BOOT_IO);
+    io.push((0xFF02, if self.is_color() { 0x7F } else { 0x7E })); // SC
+
+    io
+  }
+
+  // Only the register value, as writing it would start a transfer
+  pub fn post_boot_dma_source(self) -> u8 {
+    if self.is_color() { 0x00 } else { 0xFF }
+  }
+
+  // The internal counter DIV is the upper byte of. The SGB and CGB bootroms
+  // take a variable time to run, so their value is only approximate
+  pub fn post_boot_divider(self) -> u16 {
+    match self {
+      Model::Dmg0 => 0x1830,
+      Model::Dmg | Model::Mgb => 0xABCC,
+      Model::Sgb | Model::Sgb2 => 0x0000,
+      Model::Cgb | Model::Agb => 0x1EA0,
+    }
+  }
+}
@@ -0,0 +1,390 @@
+use std::fs::File;
+use std::io::{self, Write};
+use std::iter;
+use std::path::{Path, PathBuf};
+
+use super::serial::SerialDevice;
+
+const MAGIC: [u8; 2] = [0x88, 0x33];
+
+const COMMAND_INIT: u8 = 0x01;
+const COMMAND_PRINT: u8 = 0x02;
+const COMMAND_DATA: u8 = 0x04;
+const COMMAND_STATUS: u8 = 0x0F;
+
+// Replies to the two bytes sent after a packet
+const ALIVE: u8 = 0x81;
+
+const STATUS_CHECKSUM_ERROR: u8 = 0x01;
+const STATUS_BUSY: u8 = 0x02;
+const STATUS_FULL: u8 = 0x04;
+const STATUS_UNPROCESSED: u8 = 0x08;
+const STATUS_PACKET_ERROR: u8 = 0x10;
+
+// The printer's RAM, as 160x200 pixels of tile data
+const BUFFER_SIZE: usize = 0x1F40;
+
+// Tiles are laid out in rows of 20, each 8x8 pixels at 2 bits per pixel
+const WIDTH: usize = 160;
+const TILES_PER_ROW: usize = WIDTH / 8;
+const TILE_SIZE: usize = 16;
+
+// Roughly how long the paper takes to feed through, in CPU cycles
+const PRINT_CYCLES: u32 = 4_194_304;
+
+// Palettes map colors to shades from 0 (white) to 3 (black). Zero selects this one
+const DEFAULT_PALETTE: u8 = 0xE4;
+const GREY_LEVELS: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+
+// The RLE compression flag of a packet
+const COMPRESSED: u8 = 0x01;
+const RUN_FLAG: u8 = 0x80;
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+  Magic(usize),
+  Command,
+  Compression,
+  LengthLow,
+  LengthHigh,
+  Data,
+  ChecksumLow,
+  ChecksumHigh,
+  Alive,
+  Status,
+}
+
+// The Game Boy Printer, which saves each printout into a directory as a PGM image
+pub struct Printer {
+  directory: PathBuf,
+  state: State,
+  command: u8,
+  compression: u8,
+  length: u16,
+  data: Vec<u8>, // Of the packet being received
+  checksum: u16,
+  status: u8,
+  buffer: Vec<u8>, // Tile data waiting to be printed
+  image: Vec<u8>, // Grey levels printed since the last margin
+  busy_cycles: u32,
+  printouts: u32,
+}
+
+impl Printer {
+  pub fn new<P: Into<PathBuf>>(directory: P) -> Printer {
+    Printer {
+      directory: directory.into(),
+      state: State::Magic(0),
+      command: 0,
+      compression: 0,
+      length: 0,
+      data: Vec::new(),
+      checksum: 0,
+      status: 0,
+      buffer: Vec::new(),
+      image: Vec::new(),
+      busy_cycles: 0,
+      printouts: 0,
+    }
+  }
+
+  // Returns the byte shifted out at the same time as `value` is received
+  fn receive(&mut self, value: u8) -> u8 {
+    let mut reply = 0x00;
+
+    self.state = match self.state {
+      State::Magic(index) if value == MAGIC[index] => {
+        if index + 1 < MAGIC.len() { State::Magic(index + 1) } else { State::Command }
+      }
+      State::Magic(_) => State::Magic(if value == MAGIC[0] { 1 } else { 0 }),
+      State::Command => {
+        self.command = value;
+        self.checksum = value as u16;
+
+        State::Compression
+      }
+      State::Compression => {
+        self.compression = value;
+        self.checksum = self.checksum.wrapping_add(value as u16);
+
+        State::LengthLow
+      }
+      State::LengthLow => {
+        self.length = value as u16;
+        self.checksum = self.checksum.wrapping_add(value as u16);
+
+        State::LengthHigh
+      }
+      State::LengthHigh => {
+        self.length |= (value as u16) << 8;
+        self.checksum = self.checksum.wrapping_add(value as u16);
+        self.data.clear();
+
+        if self.length == 0 { State::ChecksumLow } else { State::Data }
+      }
+      State::Data => {
+        self.data.push(value);
+        self.checksum = self.checksum.wrapping_add(value as u16);
+
+        if self.data.len() < self.length as usize { State::Data } else { State::ChecksumLow }
+      }
+      State::ChecksumLow => {
+        self.checksum = self.checksum.wrapping_sub(value as u16);
+
+        State::ChecksumHigh
+      }
+      State::ChecksumHigh => {
+        self.checksum = self.checksum.wrapping_sub((value as u16) << 8);
+
+        if self.checksum == 0 {
+          self.status &= !STATUS_CHECKSUM_ERROR;
+          self.execute();
+        } else {
+          self.status |= STATUS_CHECKSUM_ERROR;
+        }
+
+        State::Alive
+      }
+      State::Alive => {
+        reply = ALIVE;
+
+        State::Status
+      }
+      State::Status => {
+        reply = self.status;
+
+        State::Magic(0)
+      }
+    };
+
+    reply
+  }
+
+  fn execute(&mut self) {
+    match self.command {
+      COMMAND_INIT => {
+        self.buffer.clear();
+        self.status = 0;
+      }
+      COMMAND_DATA => {
+        if self.compression & COMPRESSED != 0 {
+          self.decompress();
+        } else {
+          self.buffer.extend_from_slice(&self.data);
+        }
+
+        // An empty packet marks the end of the data, but changes nothing
+        if !self.data.is_empty() {
+          self.status |= STATUS_UNPROCESSED;
+        }
+
+        if self.buffer.len() >= BUFFER_SIZE {
+          self.buffer.truncate(BUFFER_SIZE);
+          self.status |= STATUS_FULL;
+        }
+      }
+      COMMAND_PRINT if self.data.len() == 4 => {
+        // Sheets, margins, palette and exposure, the last of which is ignored
+        let margins = self.data[1];
+        let palette = if self.data[2] == 0 { DEFAULT_PALETTE } else { self.data[2] };
+
+        self.print(palette);
+
+        // The feed after the image ends the printout
+        if margins & 0x0F != 0 {
+          self.save();
+        }
+
+        self.status = (self.status & !(STATUS_UNPROCESSED | STATUS_FULL)) | STATUS_BUSY;
+        self.busy_cycles = PRINT_CYCLES;
+      }
+      COMMAND_STATUS => {}
+      _ => self.status |= STATUS_PACKET_ERROR,
+    }
+  }
+
+  // A control byte with the top bit set repeats the next byte (control & 0x7F) + 2 times,
+  // otherwise the next (control + 1) bytes are copied as they are
+  fn decompress(&mut self) {
+    let mut bytes = self.data.iter().cloned();
+
+    while let Some(control) = bytes.next() {
+      if control & RUN_FLAG != 0 {
+        if let Some(value) = bytes.next() {
+          let count = (control & !RUN_FLAG) as usize + 2;
+
+          self.buffer.extend(iter::repeat_n(value, count));
+        }
+      } else {
+        self.buffer.extend(bytes.by_ref().take(control as usize + 1));
+      }
+    }
+  }
+
+  fn print(&mut self, palette: u8) {
+    let rows = self.buffer.len() / (TILES_PER_ROW * TILE_SIZE);
+
+    for row in 0..rows * 8 {
+      for x in 0..WIDTH {
+        let address = ((row / 8) * TILES_PER_ROW + x / 8) * TILE_SIZE + (row % 8) * 2;
+        let bit = 7 - (x % 8);
+        let color = ((self.buffer[address] >> bit) & 0x01) | (((self.buffer[address + 1] >> bit) & 0x01) << 1);
+        let shade = (palette >> (color * 2)) & 0x03;
+
+        self.image.push(GREY_LEVELS[shade as usize]);
+      }
+    }
+
+    self.buffer.clear();
+  }
+
+  fn save(&mut self) {
+    if self.image.is_empty() {
+      return;
+    }
+
+    self.printouts += 1;
+
+    let path = self.directory.join(format!("printout_{:03}.pgm", self.printouts));
+
+    match write_pgm(&path, &self.image) {
+      Ok(()) => println!("Printed: {}", path.display()),
+      Err(error) => eprintln!("Failed to write printout {}: {}", path.display(), error),
+    }
+
+    self.image.clear();
+  }
+}
+
+impl SerialDevice for Printer {
+  fn transfer(&mut self, value: u8) -> Option<u8> {
+    Some(self.receive(value))
+  }
+
+  fn tick(&mut self, cycles: u32, _waiting: Option<u8>) -> Option<u8> {
+    if self.busy_cycles > 0 {
+      self.busy_cycles = self.busy_cycles.saturating_sub(cycles);
+
+      if self.busy_cycles == 0 {
+        self.status &= !STATUS_BUSY;
+      }
+    }
+
+    None
+  }
+}
+
+// Binary greymap, with one byte per pixel
+fn write_pgm(path: &Path, image: &[u8]) -> io::Result<()> {
+  let mut file = File::create(path)?;
+
+  write!(file, "P5\n{} {}\n255\n", WIDTH, image.len() / WIDTH)?;
+  file.write_all(image)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::env;
+  use std::fs;
+  use std::process;
+
+  const ROW_SIZE: usize = TILES_PER_ROW * TILE_SIZE;
+
+  // Sends a whole packet, returning the two bytes shifted back at its end
+  fn send(printer: &mut Printer, command: u8, compression: u8, data: &[u8], checksum: u16) -> (u8, u8) {
+    let mut packet = MAGIC.to_vec();
+
+    packet.extend(&[command, compression, data.len() as u8, (data.len() >> 8) as u8]);
+    packet.extend(data);
+    packet.extend(&[checksum as u8, (checksum >> 8) as u8]);
+
+    for &value in &packet {
+      assert_eq!(printer.receive(value), 0x00);
+    }
+
+    (printer.receive(0x00), printer.receive(0x00))
+  }
+
+  fn checksum(command: u8, compression: u8, data: &[u8]) -> u16 {
+    let header = [command, compression, data.len() as u8, (data.len() >> 8) as u8];
+
+    header.iter().chain(data).fold(0, |sum: u16, &value| sum.wrapping_add(value as u16))
+  }
+
+  fn send_packet(printer: &mut Printer, command: u8, compression: u8, data: &[u8]) -> (u8, u8) {
+    send(printer, command, compression, data, checksum(command, compression, data))
+  }
+
+  // Two rows of tiles: colour 1 sent as it is, then colour 3 compressed
+  fn load_image(printer: &mut Printer) {
+    let raw: Vec<u8> = (0..ROW_SIZE).map(|index| if index % 2 == 0 { 0xFF } else { 0x00 }).collect();
+
+    assert_eq!(send_packet(printer, COMMAND_DATA, 0, &raw), (ALIVE, STATUS_UNPROCESSED));
+
+    // Two bytes copied, then runs of 128, 128 and 62
+    let compressed = [0x01, 0xFF, 0xFF, RUN_FLAG | 126, 0xFF, RUN_FLAG | 126, 0xFF, RUN_FLAG | 60, 0xFF];
+
+    assert_eq!(send_packet(printer, COMMAND_DATA, COMPRESSED, &compressed), (ALIVE, STATUS_UNPROCESSED));
+    assert_eq!(printer.buffer.len(), 2 * ROW_SIZE);
+
+    assert_eq!(send_packet(printer, COMMAND_DATA, 0, &[]), (ALIVE, STATUS_UNPROCESSED));
+  }
+
+  #[test]
+  fn prints_raw_and_compressed_data() {
+    let mut printer = Printer::new(env::temp_dir());
+
+    assert_eq!(send_packet(&mut printer, COMMAND_INIT, 0, &[]), (ALIVE, 0x00));
+    load_image(&mut printer);
+
+    // One sheet without a feed afterwards, so nothing is saved yet
+    assert_eq!(send_packet(&mut printer, COMMAND_PRINT, 0, &[0x01, 0x00, 0xE4, 0x40]), (ALIVE, STATUS_BUSY));
+    assert_eq!(printer.image.len(), 16 * WIDTH);
+    assert!(printer.image[..8 * WIDTH].iter().all(|&grey| grey == GREY_LEVELS[1]));
+    assert!(printer.image[8 * WIDTH..].iter().all(|&grey| grey == GREY_LEVELS[3]));
+    assert_eq!(printer.printouts, 0);
+
+    printer.tick(PRINT_CYCLES, None);
+    assert_eq!(send_packet(&mut printer, COMMAND_STATUS, 0, &[]), (ALIVE, 0x00));
+  }
+
+  #[test]
+  fn flags_checksum_errors() {
+    let mut printer = Printer::new(env::temp_dir());
+    let data = [0x12; 16];
+
+    assert_eq!(send(&mut printer, COMMAND_DATA, 0, &data, 0x0000), (ALIVE, STATUS_CHECKSUM_ERROR));
+    assert!(printer.buffer.is_empty());
+
+    // The next good packet clears the error
+    assert_eq!(send_packet(&mut printer, COMMAND_DATA, 0, &data), (ALIVE, STATUS_UNPROCESSED));
+    assert_eq!(printer.buffer, data);
+  }
+
+  #[test]
+  fn saves_printouts_after_the_feed() {
+    let directory = env::temp_dir().join(format!("rustboy-printer-{}", process::id()));
+
+    fs::create_dir_all(&directory).unwrap();
+
+    let mut printer = Printer::new(&directory);
+
+    load_image(&mut printer);
+    send_packet(&mut printer, COMMAND_PRINT, 0, &[0x01, 0x03, 0x00, 0x40]);
+
+    let path = directory.join("printout_001.pgm");
+    let file = fs::read(&path).unwrap();
+    let header = b"P5\n160 16\n255\n";
+
+    let mut image = vec![GREY_LEVELS[1]; 8 * WIDTH];
+
+    image.extend(&[GREY_LEVELS[3]; 8 * WIDTH]);
+
+    assert_eq!(file[..header.len()], header[..]);
+    assert_eq!(file[header.len()..], image[..]);
+    assert!(printer.image.is_empty());
+
+    fs::remove_dir_all(&directory).unwrap();
+  }
+}
@@ -1,5 +1,6 @@
 use std::fmt;
 use super::opcode::*;
+use super::registers::*;
 
 use num::FromPrimitive;
 
@@ -7,14 +8,18 @@ use num::FromPrimitive;
 pub struct Instruction(pub u8);
 
 impl Instruction {
-  pub fn opcode(&self) -> Opcode {
-    Opcode::from_u8(self.0).unwrap_or_else(|| panic!("Unrecognized instruction: {:#04x}", self.0))
+  // None for the eleven opcodes the CPU doesn't implement
+  pub fn opcode(&self) -> Option<Opcode> {
+    Opcode::from_u8(self.0)
   }
 }
 
 impl fmt::Debug for Instruction {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{:#04x} {:?}", self.0, self.opcode())
+    match self.opcode() {
+      Some(opcode) => write!(f, "{:#04x} {:?}", self.0, opcode),
+      None => write!(f, "{:#04x} Illegal", self.0),
+    }
   }
 }
 
@@ -23,12 +28,47 @@ pub struct SpecialInstruction(pub u8);
 
 impl SpecialInstruction {
   pub fn opcode(&self) -> SpecialOpcode {
-    SpecialOpcode::from_u8(self.0).unwrap_or_else(|| panic!("Unrecognized special instruction: {:#04x}", self.0))
+    let index = (self.0 >> 3) & 0x07;
+
+    match self.0 >> 6 {
+      0 => match index {
+        0 => SpecialOpcode::RotateLeftCircular,
+        1 => SpecialOpcode::RotateRightCircular,
+        2 => SpecialOpcode::RotateLeft,
+        3 => SpecialOpcode::RotateRight,
+        4 => SpecialOpcode::ShiftLeftArithmetic,
+        5 => SpecialOpcode::ShiftRightArithmetic,
+        6 => SpecialOpcode::Swap,
+        _ => SpecialOpcode::ShiftRightLogical,
+      },
+      1 => SpecialOpcode::Bit(index),
+      2 => SpecialOpcode::Reset(index),
+      _ => SpecialOpcode::Set(index),
+    }
+  }
+
+  pub fn operand(&self) -> Operand {
+    match self.0 & 0x07 {
+      0 => Operand::Register(REG_B),
+      1 => Operand::Register(REG_C),
+      2 => Operand::Register(REG_D),
+      3 => Operand::Register(REG_E),
+      4 => Operand::Register(REG_H),
+      5 => Operand::Register(REG_L),
+      6 => Operand::AddrHl,
+      _ => Operand::Register(REG_A),
+    }
   }
 }
 
 impl fmt::Debug for SpecialInstruction {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    write!(f, "{:#04x} {:?}", self.0, self.opcode())
+    write!(f, "{:#04x} {:?} {:?}", self.0, self.opcode(), self.operand())
   }
 }
+
+#[derive(Clone, Copy, Debug)]
+pub enum Operand {
+  Register(ByteRegister),
+  AddrHl,
+}
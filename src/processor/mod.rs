@@ -1,51 +1,154 @@
 mod opcode;
 mod registers;
 mod instruction;
+#[cfg(test)]
+mod tests;
 
 use std::fmt;
 
-use super::memory::{Memory, IO_BASE_REG};
+pub use self::registers::Registers;
+
+use super::memory::{Memory, IO_BASE_REG, JOYPAD_REG, DIVIDER_REG, INTERRUPT_FLAG_REG, INTERRUPT_ENABLE_REG};
+use super::interrupts::Interrupt;
 
 use self::opcode::*;
 use self::registers::*;
 use self::instruction::*;
 
+// Every memory access takes one machine cycle (M-cycle) of four clock cycles (T-cycles)
+pub const CYCLES_PER_MACHINE_CYCLE: u32 = 4;
+
+// M-cycles the CPU is paused for while a CGB speed switch takes place
+const SPEED_SWITCH_MACHINE_CYCLES: u32 = 2050;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum State {
+  Running,
+  Halted, // Waiting for an interrupt
+  Stopped, // Waiting for a button press, with the system clock stopped
+  Locked, // Hung by an illegal opcode until power off
+}
+
 pub struct Processor<M: Memory> {
   registers: Registers, // General Purpose Registers
+  cycles: u64, // T-cycles elapsed since power on
+  ime_delay: u8, // Instructions left until EI takes effect
+  state: State,
+  halt_bug: bool, // The next opcode fetch fails to increment PC
 
   memory: M,
 }
 
 impl<M: Memory> Processor<M> {
   pub fn new(memory: M) -> Self {
+    Processor::with_registers(Registers::new(), memory)
+  }
+
+  pub fn with_registers(registers: Registers, memory: M) -> Self {
     Processor {
-      registers: Registers::new(),
+      registers,
+      cycles: 0,
+      ime_delay: 0,
+      state: State::Running,
+      halt_bug: false,
 
-      memory: memory,
+      memory,
     }
   }
 
-  pub fn step(&mut self) {
-    let instruction = self.read_instruction();
+  // Executes a single instruction and returns the number of T-cycles it took
+  pub fn step(&mut self) -> u32 {
+    let start = self.cycles;
+
+    match self.state {
+      State::Halted => {
+        if self.pending_interrupt().is_none() {
+          self.tick();
+
+          return CYCLES_PER_MACHINE_CYCLE;
+        }
+
+        self.state = State::Running;
+      }
+
+      State::Stopped => {
+        if !self.button_held() {
+          self.cycles += CYCLES_PER_MACHINE_CYCLE as u64;
+
+          return CYCLES_PER_MACHINE_CYCLE;
+        }
+
+        self.state = State::Running;
+      }
+
+      // The rest of the system keeps running, but nothing is ever fetched again
+      State::Locked => {
+        self.tick();
+
+        return CYCLES_PER_MACHINE_CYCLE;
+      }
+
+      State::Running => {}
+    }
+
+    if self.registers.get_interrupt_master_enable() && self.pending_interrupt().is_some() {
+      self.service_interrupt();
+    } else {
+      let instruction = self.read_instruction();
+
+      self.execute_instruction(instruction);
+    }
+
+    if self.ime_delay > 0 {
+      self.ime_delay -= 1;
+
+      if self.ime_delay == 0 {
+        self.registers.set_interrupt_master_enable(true);
+      }
+    }
+
+    (self.cycles - start) as u32
+  }
+
+  pub fn cycles(&self) -> u64 {
+    self.cycles
+  }
+
+  pub fn memory(&self) -> &M {
+    &self.memory
+  }
 
-    self.execute_instruction(instruction);
+  pub fn memory_mut(&mut self) -> &mut M {
+    &mut self.memory
   }
 
   fn read_instruction(&mut self) -> Instruction {
     let pc = self.registers.get_program_counter();
-    let address = M::B::from(pc);
 
-    let instruction = Instruction(self.memory.read_byte(address));
+    let instruction = Instruction(self.read_memory(pc));
 
     println!("PC: {:#06x}: {:?}", pc, instruction);
 
-    self.registers.increment_program_counter(1);
+    if self.halt_bug {
+      self.halt_bug = false;
+    } else {
+      self.registers.increment_program_counter(1);
+    }
 
     instruction
   }
 
   fn execute_instruction(&mut self, instruction: Instruction) {
-    match instruction.opcode() {
+    let opcode = match instruction.opcode() {
+      Some(opcode) => opcode,
+      None => {
+        self.state = State::Locked;
+
+        return;
+      }
+    };
+
+    match opcode {
       /*
         ***********
         * Control *
@@ -56,12 +159,36 @@ impl<M: Memory> Processor<M> {
         // No operation
       }
 
+      Opcode::Stop /* 0x10 */ => { self.stop(); }
+
+      Opcode::Halt /* 0x76 */ => {
+        if self.registers.get_interrupt_master_enable() || self.pending_interrupt().is_none() {
+          self.state = State::Halted;
+        } else {
+          // With IME clear and an interrupt already pending HALT is skipped and
+          // the following byte is read twice
+          self.halt_bug = true;
+        }
+      }
+
       Opcode::Special /* 0xCB */ => {
         let special_instruction = self.read_special_instruction();
 
         self.execute_special_instruction(special_instruction);
       }
 
+      Opcode::DisableInterrupts /* 0xF3 */ => {
+        self.registers.set_interrupt_master_enable(false);
+        self.ime_delay = 0;
+      }
+
+      Opcode::EnableInterrupts /* 0xFB */ => {
+        // IME is only set once the instruction following EI has executed
+        if !self.registers.get_interrupt_master_enable() {
+          self.ime_delay = 2;
+        }
+      }
+
       /*
         ****************
         * Flow Control *
@@ -71,7 +198,7 @@ impl<M: Memory> Processor<M> {
       Opcode::Jump /* 0xC3 */ => {
         let value = self.read_immediate_word();
 
-        self.registers.set_program_counter(value);
+        self.jump(value);
       }
 
       Opcode::JumpNonZero /* 0xC2 */ => { self.jump_conditionally(ZERO_FLAG, false); }
@@ -82,10 +209,16 @@ impl<M: Memory> Processor<M> {
 
       Opcode::JumpCarry /* 0xDA */ => { self.jump_conditionally(CARRY_FLAG, true); }
 
+      Opcode::JumpHl /* 0xE9 */ => {
+        let hl = self.registers.read_word(REG_HL);
+
+        self.registers.set_program_counter(hl);
+      }
+
       Opcode::JumpRelative /* 0x18 */ => {
         let offset = self.read_immediate_byte();
 
-        self.registers.increment_program_counter((offset as i8) as i16);
+        self.jump_relative(offset);
       }
 
       Opcode::JumpRelativeNonZero /* 0x20 */ => { self.jump_relative_conditionally(ZERO_FLAG, false); }
@@ -96,34 +229,183 @@ impl<M: Memory> Processor<M> {
 
       Opcode::JumpRelativeCarry /* 0x38 */ => { self.jump_relative_conditionally(CARRY_FLAG, true); }
 
-      Opcode::Return /* 0xC9 */ => {
-        let value = self.stack_pop();
+      Opcode::Return /* 0xC9 */ => { self.ret(); }
+
+      Opcode::ReturnNonZero /* 0xC0 */ => { self.return_conditionally(ZERO_FLAG, false); }
 
-        self.registers.set_program_counter(value);
+      Opcode::ReturnZero /* 0xC8 */ => { self.return_conditionally(ZERO_FLAG, true); }
+
+      Opcode::ReturnNonCarry /* 0xD0 */ => { self.return_conditionally(CARRY_FLAG, false); }
+
+      Opcode::ReturnCarry /* 0xD8 */ => { self.return_conditionally(CARRY_FLAG, true); }
+
+      Opcode::ReturnFromInterrupt /* 0xD9 */ => {
+        self.ret();
+        self.registers.set_interrupt_master_enable(true);
       }
 
       Opcode::CallImmAddr /* 0xCD */ => {
         let value = self.read_immediate_word();
-        let pc = self.registers.get_program_counter();
 
-        self.stack_push(pc);
-        self.registers.set_program_counter(value);
+        self.call(value);
       }
 
+      Opcode::CallNonZero /* 0xC4 */ => { self.call_conditionally(ZERO_FLAG, false); }
+
+      Opcode::CallZero /* 0xCC */ => { self.call_conditionally(ZERO_FLAG, true); }
+
+      Opcode::CallNonCarry /* 0xD4 */ => { self.call_conditionally(CARRY_FLAG, false); }
+
+      Opcode::CallCarry /* 0xDC */ => { self.call_conditionally(CARRY_FLAG, true); }
+
+      Opcode::Restart00 /* 0xC7 */ => { self.call(0x0000); }
+
+      Opcode::Restart08 /* 0xCF */ => { self.call(0x0008); }
+
+      Opcode::Restart10 /* 0xD7 */ => { self.call(0x0010); }
+
+      Opcode::Restart18 /* 0xDF */ => { self.call(0x0018); }
+
+      Opcode::Restart20 /* 0xE7 */ => { self.call(0x0020); }
+
+      Opcode::Restart28 /* 0xEF */ => { self.call(0x0028); }
+
+      Opcode::Restart30 /* 0xF7 */ => { self.call(0x0030); }
+
+      Opcode::Restart38 /* 0xFF */ => { self.call(0x0038); }
+
       /*
         **************
         * 8-bit Load *
         **************
       */
 
+      Opcode::LoadBIntoB /* 0x40 */ => { self.registers.transfer_byte(REG_B, REG_B); }
+
+      Opcode::LoadCIntoB /* 0x41 */ => { self.registers.transfer_byte(REG_C, REG_B); }
+
+      Opcode::LoadDIntoB /* 0x42 */ => { self.registers.transfer_byte(REG_D, REG_B); }
+
+      Opcode::LoadEIntoB /* 0x43 */ => { self.registers.transfer_byte(REG_E, REG_B); }
+
+      Opcode::LoadHIntoB /* 0x44 */ => { self.registers.transfer_byte(REG_H, REG_B); }
+
+      Opcode::LoadLIntoB /* 0x45 */ => { self.registers.transfer_byte(REG_L, REG_B); }
+
+      Opcode::LoadAddrHlIntoB /* 0x46 */ => { self.transfer_from_address(REG_HL, REG_B); }
+
+      Opcode::LoadAIntoB /* 0x47 */ => { self.registers.transfer_byte(REG_A, REG_B); }
+
+      Opcode::LoadBIntoC /* 0x48 */ => { self.registers.transfer_byte(REG_B, REG_C); }
+
+      Opcode::LoadCIntoC /* 0x49 */ => { self.registers.transfer_byte(REG_C, REG_C); }
+
+      Opcode::LoadDIntoC /* 0x4A */ => { self.registers.transfer_byte(REG_D, REG_C); }
+
+      Opcode::LoadEIntoC /* 0x4B */ => { self.registers.transfer_byte(REG_E, REG_C); }
+
+      Opcode::LoadHIntoC /* 0x4C */ => { self.registers.transfer_byte(REG_H, REG_C); }
+
+      Opcode::LoadLIntoC /* 0x4D */ => { self.registers.transfer_byte(REG_L, REG_C); }
+
+      Opcode::LoadAddrHlIntoC /* 0x4E */ => { self.transfer_from_address(REG_HL, REG_C); }
+
       Opcode::LoadAIntoC /* 0x4F */ => { self.registers.transfer_byte(REG_A, REG_C); }
 
+      Opcode::LoadBIntoD /* 0x50 */ => { self.registers.transfer_byte(REG_B, REG_D); }
+
+      Opcode::LoadCIntoD /* 0x51 */ => { self.registers.transfer_byte(REG_C, REG_D); }
+
+      Opcode::LoadDIntoD /* 0x52 */ => { self.registers.transfer_byte(REG_D, REG_D); }
+
+      Opcode::LoadEIntoD /* 0x53 */ => { self.registers.transfer_byte(REG_E, REG_D); }
+
+      Opcode::LoadHIntoD /* 0x54 */ => { self.registers.transfer_byte(REG_H, REG_D); }
+
+      Opcode::LoadLIntoD /* 0x55 */ => { self.registers.transfer_byte(REG_L, REG_D); }
+
+      Opcode::LoadAddrHlIntoD /* 0x56 */ => { self.transfer_from_address(REG_HL, REG_D); }
+
       Opcode::LoadAIntoD /* 0x57 */ => { self.registers.transfer_byte(REG_A, REG_D); }
 
+      Opcode::LoadBIntoE /* 0x58 */ => { self.registers.transfer_byte(REG_B, REG_E); }
+
+      Opcode::LoadCIntoE /* 0x59 */ => { self.registers.transfer_byte(REG_C, REG_E); }
+
+      Opcode::LoadDIntoE /* 0x5A */ => { self.registers.transfer_byte(REG_D, REG_E); }
+
+      Opcode::LoadEIntoE /* 0x5B */ => { self.registers.transfer_byte(REG_E, REG_E); }
+
+      Opcode::LoadHIntoE /* 0x5C */ => { self.registers.transfer_byte(REG_H, REG_E); }
+
+      Opcode::LoadLIntoE /* 0x5D */ => { self.registers.transfer_byte(REG_L, REG_E); }
+
+      Opcode::LoadAddrHlIntoE /* 0x5E */ => { self.transfer_from_address(REG_HL, REG_E); }
+
+      Opcode::LoadAIntoE /* 0x5F */ => { self.registers.transfer_byte(REG_A, REG_E); }
+
+      Opcode::LoadBIntoH /* 0x60 */ => { self.registers.transfer_byte(REG_B, REG_H); }
+
+      Opcode::LoadCIntoH /* 0x61 */ => { self.registers.transfer_byte(REG_C, REG_H); }
+
+      Opcode::LoadDIntoH /* 0x62 */ => { self.registers.transfer_byte(REG_D, REG_H); }
+
+      Opcode::LoadEIntoH /* 0x63 */ => { self.registers.transfer_byte(REG_E, REG_H); }
+
+      Opcode::LoadHIntoH /* 0x64 */ => { self.registers.transfer_byte(REG_H, REG_H); }
+
+      Opcode::LoadLIntoH /* 0x65 */ => { self.registers.transfer_byte(REG_L, REG_H); }
+
+      Opcode::LoadAddrHlIntoH /* 0x66 */ => { self.transfer_from_address(REG_HL, REG_H); }
+
       Opcode::LoadAIntoH /* 0x67 */ => { self.registers.transfer_byte(REG_A, REG_H); }
 
+      Opcode::LoadBIntoL /* 0x68 */ => { self.registers.transfer_byte(REG_B, REG_L); }
+
+      Opcode::LoadCIntoL /* 0x69 */ => { self.registers.transfer_byte(REG_C, REG_L); }
+
+      Opcode::LoadDIntoL /* 0x6A */ => { self.registers.transfer_byte(REG_D, REG_L); }
+
+      Opcode::LoadEIntoL /* 0x6B */ => { self.registers.transfer_byte(REG_E, REG_L); }
+
+      Opcode::LoadHIntoL /* 0x6C */ => { self.registers.transfer_byte(REG_H, REG_L); }
+
+      Opcode::LoadLIntoL /* 0x6D */ => { self.registers.transfer_byte(REG_L, REG_L); }
+
+      Opcode::LoadAddrHlIntoL /* 0x6E */ => { self.transfer_from_address(REG_HL, REG_L); }
+
+      Opcode::LoadAIntoL /* 0x6F */ => { self.registers.transfer_byte(REG_A, REG_L); }
+
+      Opcode::LoadBIntoAddrHl /* 0x70 */ => { self.transfer_to_address(REG_B, REG_HL); }
+
+      Opcode::LoadCIntoAddrHl /* 0x71 */ => { self.transfer_to_address(REG_C, REG_HL); }
+
+      Opcode::LoadDIntoAddrHl /* 0x72 */ => { self.transfer_to_address(REG_D, REG_HL); }
+
+      Opcode::LoadEIntoAddrHl /* 0x73 */ => { self.transfer_to_address(REG_E, REG_HL); }
+
+      Opcode::LoadHIntoAddrHl /* 0x74 */ => { self.transfer_to_address(REG_H, REG_HL); }
+
+      Opcode::LoadLIntoAddrHl /* 0x75 */ => { self.transfer_to_address(REG_L, REG_HL); }
+
+      Opcode::LoadAIntoAddrHl /* 0x77 */ => { self.transfer_to_address(REG_A, REG_HL); }
+
+      Opcode::LoadBIntoA /* 0x78 */ => { self.registers.transfer_byte(REG_B, REG_A); }
+
+      Opcode::LoadCIntoA /* 0x79 */ => { self.registers.transfer_byte(REG_C, REG_A); }
+
+      Opcode::LoadDIntoA /* 0x7A */ => { self.registers.transfer_byte(REG_D, REG_A); }
+
       Opcode::LoadEIntoA /* 0x7B */ => { self.registers.transfer_byte(REG_E, REG_A); }
 
+      Opcode::LoadHIntoA /* 0x7C */ => { self.registers.transfer_byte(REG_H, REG_A); }
+
+      Opcode::LoadLIntoA /* 0x7D */ => { self.registers.transfer_byte(REG_L, REG_A); }
+
+      Opcode::LoadAddrHlIntoA /* 0x7E */ => { self.transfer_from_address(REG_HL, REG_A); }
+
+      Opcode::LoadAIntoA /* 0x7F */ => { self.registers.transfer_byte(REG_A, REG_A); }
+
       Opcode::LoadImmIntoB /* 0x06 */ => { self.load_immediate_byte(REG_B); }
 
       Opcode::LoadImmIntoC /* 0x0E */ => { self.load_immediate_byte(REG_C); }
@@ -141,31 +423,36 @@ impl<M: Memory> Processor<M> {
       Opcode::LoadImmIntoAddrHl /* 0x36 */ => {
         let value = self.read_immediate_byte();
         let hl = self.registers.read_word(REG_HL);
-        let address = M::B::from(hl);
 
-        self.memory.write_byte(address, value);
+        self.write_memory(hl, value);
       }
 
       Opcode::LoadAIntoAddrC /* 0xE2 */ => {
         let a = self.registers.read_byte(REG_A);
         let c = self.registers.read_byte(REG_C);
-        let address = M::B::from(IO_BASE_REG + c as u16);
 
-        self.memory.write_byte(address, a);
+        self.write_memory(IO_BASE_REG + c as u16, a);
+      }
+
+      Opcode::LoadAddrCIntoA /* 0xF2 */ => {
+        let c = self.registers.read_byte(REG_C);
+        let value = self.read_memory(IO_BASE_REG + c as u16);
+
+        self.registers.write_byte(REG_A, value);
       }
 
       Opcode::LoadAIntoAddrBc /* 0x02 */ => { self.transfer_to_address(REG_A, REG_BC); }
 
       Opcode::LoadAIntoAddrDe /* 0x12 */ => { self.transfer_to_address(REG_A, REG_DE); }
 
-      Opcode::LoadAIntoAddrHl /* 0x77 */ => { self.transfer_to_address(REG_A, REG_HL); }
-
       Opcode::LoadAddrBcIntoA /* 0x0A */ => { self.transfer_from_address(REG_BC, REG_A); }
 
       Opcode::LoadAddrDeIntoA /* 0x1A */ => { self.transfer_from_address(REG_DE, REG_A); }
 
       Opcode::LoadAIntoImmAddr /* 0xEA */ => { self.transfer_to_immediate_address(REG_A); }
 
+      Opcode::LoadImmAddrIntoA /* 0xFA */ => { self.transfer_from_immediate_address(REG_A); }
+
       Opcode::LoadAIntoAddrHlAndInc /* 0x22 */ => {
         self.transfer_to_address(REG_A, REG_HL);
         self.registers.increment_word(REG_HL);
@@ -189,15 +476,13 @@ impl<M: Memory> Processor<M> {
       Opcode::LoadAIntoImmAddrIO /* 0xE0 */ => {
         let a = self.registers.read_byte(REG_A);
         let value = self.read_immediate_byte();
-        let address = M::B::from(IO_BASE_REG + value as u16);
 
-        self.memory.write_byte(address, a);
+        self.write_memory(IO_BASE_REG + value as u16, a);
       }
 
       Opcode::LoadImmAddrIOIntoA /* 0xF0 */ => {
         let value = self.read_immediate_byte();
-        let address = M::B::from(IO_BASE_REG + value as u16);
-        let value = self.memory.read_byte(address);
+        let value = self.read_memory(IO_BASE_REG + value as u16);
 
         self.registers.write_byte(REG_A, value);
       }
@@ -221,55 +506,329 @@ impl<M: Memory> Processor<M> {
       }
 
       Opcode::LoadSpIntoImmAddr /* 0x08 */ => {
-        let value = self.read_immediate_word();
-        let address = M::W::from(value);
+        let address = self.read_immediate_word();
         let value = self.registers.get_stack_pointer();
 
-        self.memory.write_word(address, value);
+        self.write_memory(address, (value & 0xFF) as u8);
+        self.write_memory(address.wrapping_add(1), (value >> 8) as u8);
       }
 
-      Opcode::PopBc /* 0xC1 */ => {
-        let value = self.stack_pop();
+      Opcode::LoadHlIntoSp /* 0xF9 */ => {
+        let hl = self.registers.read_word(REG_HL);
 
-        self.registers.write_word(REG_BC, value);
+        self.registers.set_stack_pointer(hl);
+        self.tick();
       }
 
-      Opcode::PushBc /* 0xC5 */ => {
-        let bc = self.registers.read_word(REG_BC);
+      Opcode::LoadSpPlusImmIntoHl /* 0xF8 */ => {
+        let value = self.read_immediate_byte();
+        let result = self.add_to_stack_pointer(value);
 
-        self.stack_push(bc);
+        self.registers.write_word(REG_HL, result);
+        self.tick();
       }
 
+      Opcode::PopBc /* 0xC1 */ => { self.pop(REG_BC); }
+
+      Opcode::PopDe /* 0xD1 */ => { self.pop(REG_DE); }
+
+      Opcode::PopHl /* 0xE1 */ => { self.pop(REG_HL); }
+
+      Opcode::PopAf /* 0xF1 */ => {
+        let value = self.stack_pop();
+
+        // The lower nibble of F is hard-wired to zero
+        self.registers.write_word(REG_AF, value & 0xFFF0);
+      }
+
+      Opcode::PushBc /* 0xC5 */ => { self.push(REG_BC); }
+
+      Opcode::PushDe /* 0xD5 */ => { self.push(REG_DE); }
+
+      Opcode::PushHl /* 0xE5 */ => { self.push(REG_HL); }
+
+      Opcode::PushAf /* 0xF5 */ => { self.push(REG_AF); }
+
+      /*
+        **************
+        * 8-bit Math *
+        **************
+      */
+
       Opcode::IncrementB /* 0x04 */ => { self.registers.increment_byte(REG_B); }
 
       Opcode::IncrementC /* 0x0C */ => { self.registers.increment_byte(REG_C); }
 
+      Opcode::IncrementD /* 0x14 */ => { self.registers.increment_byte(REG_D); }
+
+      Opcode::IncrementE /* 0x1C */ => { self.registers.increment_byte(REG_E); }
+
+      Opcode::IncrementH /* 0x24 */ => { self.registers.increment_byte(REG_H); }
+
+      Opcode::IncrementL /* 0x2C */ => { self.registers.increment_byte(REG_L); }
+
+      Opcode::IncrementAddrHl /* 0x34 */ => {
+        let hl = self.registers.read_word(REG_HL);
+        let value = self.read_memory(hl);
+        let result = self.registers.increment(value);
+
+        self.write_memory(hl, result);
+      }
+
+      Opcode::IncrementA /* 0x3C */ => { self.registers.increment_byte(REG_A); }
+
       Opcode::DecrementB /* 0x05 */ => { self.registers.decrement_byte(REG_B); }
 
       Opcode::DecrementC /* 0x0D */ => { self.registers.decrement_byte(REG_C); }
 
+      Opcode::DecrementD /* 0x15 */ => { self.registers.decrement_byte(REG_D); }
+
+      Opcode::DecrementE /* 0x1D */ => { self.registers.decrement_byte(REG_E); }
+
+      Opcode::DecrementH /* 0x25 */ => { self.registers.decrement_byte(REG_H); }
+
+      Opcode::DecrementL /* 0x2D */ => { self.registers.decrement_byte(REG_L); }
+
+      Opcode::DecrementAddrHl /* 0x35 */ => {
+        let hl = self.registers.read_word(REG_HL);
+        let value = self.read_memory(hl);
+        let result = self.registers.decrement(value);
+
+        self.write_memory(hl, result);
+      }
+
       Opcode::DecrementA /* 0x3D */ => { self.registers.decrement_byte(REG_A); }
 
-      Opcode::SubtractL /* 0x95 */ => {
-        let a = self.registers.read_byte(REG_A);
-        let l = self.registers.read_byte(REG_L);
-        let result = self.subtract(a, l);
+      Opcode::AddB /* 0x80 */ => { self.add(self.registers.read_byte(REG_B), false); }
+
+      Opcode::AddC /* 0x81 */ => { self.add(self.registers.read_byte(REG_C), false); }
+
+      Opcode::AddD /* 0x82 */ => { self.add(self.registers.read_byte(REG_D), false); }
+
+      Opcode::AddE /* 0x83 */ => { self.add(self.registers.read_byte(REG_E), false); }
+
+      Opcode::AddH /* 0x84 */ => { self.add(self.registers.read_byte(REG_H), false); }
+
+      Opcode::AddL /* 0x85 */ => { self.add(self.registers.read_byte(REG_L), false); }
+
+      Opcode::AddAddrHl /* 0x86 */ => {
+        let value = self.read_address(REG_HL);
 
-        self.registers.write_byte(REG_A, result);
+        self.add(value, false);
       }
 
-      Opcode::XorA /* 0xAF */ => {
-        let a = self.registers.read_byte(REG_A);
-        let result = a ^ a;
+      Opcode::AddA /* 0x87 */ => { self.add(self.registers.read_byte(REG_A), false); }
+
+      Opcode::AddImm /* 0xC6 */ => {
+        let value = self.read_immediate_byte();
+
+        self.add(value, false);
+      }
+
+      Opcode::AddWithCarryB /* 0x88 */ => { self.add(self.registers.read_byte(REG_B), true); }
+
+      Opcode::AddWithCarryC /* 0x89 */ => { self.add(self.registers.read_byte(REG_C), true); }
+
+      Opcode::AddWithCarryD /* 0x8A */ => { self.add(self.registers.read_byte(REG_D), true); }
+
+      Opcode::AddWithCarryE /* 0x8B */ => { self.add(self.registers.read_byte(REG_E), true); }
+
+      Opcode::AddWithCarryH /* 0x8C */ => { self.add(self.registers.read_byte(REG_H), true); }
+
+      Opcode::AddWithCarryL /* 0x8D */ => { self.add(self.registers.read_byte(REG_L), true); }
+
+      Opcode::AddWithCarryAddrHl /* 0x8E */ => {
+        let value = self.read_address(REG_HL);
+
+        self.add(value, true);
+      }
+
+      Opcode::AddWithCarryA /* 0x8F */ => { self.add(self.registers.read_byte(REG_A), true); }
+
+      Opcode::AddWithCarryImm /* 0xCE */ => {
+        let value = self.read_immediate_byte();
+
+        self.add(value, true);
+      }
+
+      Opcode::SubtractB /* 0x90 */ => { self.subtract(self.registers.read_byte(REG_B), false); }
+
+      Opcode::SubtractC /* 0x91 */ => { self.subtract(self.registers.read_byte(REG_C), false); }
+
+      Opcode::SubtractD /* 0x92 */ => { self.subtract(self.registers.read_byte(REG_D), false); }
+
+      Opcode::SubtractE /* 0x93 */ => { self.subtract(self.registers.read_byte(REG_E), false); }
+
+      Opcode::SubtractH /* 0x94 */ => { self.subtract(self.registers.read_byte(REG_H), false); }
+
+      Opcode::SubtractL /* 0x95 */ => { self.subtract(self.registers.read_byte(REG_L), false); }
+
+      Opcode::SubtractAddrHl /* 0x96 */ => {
+        let value = self.read_address(REG_HL);
+
+        self.subtract(value, false);
+      }
+
+      Opcode::SubtractA /* 0x97 */ => { self.subtract(self.registers.read_byte(REG_A), false); }
+
+      Opcode::SubtractImm /* 0xD6 */ => {
+        let value = self.read_immediate_byte();
+
+        self.subtract(value, false);
+      }
+
+      Opcode::SubtractWithCarryB /* 0x98 */ => { self.subtract(self.registers.read_byte(REG_B), true); }
+
+      Opcode::SubtractWithCarryC /* 0x99 */ => { self.subtract(self.registers.read_byte(REG_C), true); }
+
+      Opcode::SubtractWithCarryD /* 0x9A */ => { self.subtract(self.registers.read_byte(REG_D), true); }
+
+      Opcode::SubtractWithCarryE /* 0x9B */ => { self.subtract(self.registers.read_byte(REG_E), true); }
+
+      Opcode::SubtractWithCarryH /* 0x9C */ => { self.subtract(self.registers.read_byte(REG_H), true); }
+
+      Opcode::SubtractWithCarryL /* 0x9D */ => { self.subtract(self.registers.read_byte(REG_L), true); }
+
+      Opcode::SubtractWithCarryAddrHl /* 0x9E */ => {
+        let value = self.read_address(REG_HL);
+
+        self.subtract(value, true);
+      }
+
+      Opcode::SubtractWithCarryA /* 0x9F */ => { self.subtract(self.registers.read_byte(REG_A), true); }
+
+      Opcode::SubtractWithCarryImm /* 0xDE */ => {
+        let value = self.read_immediate_byte();
+
+        self.subtract(value, true);
+      }
+
+      Opcode::AndB /* 0xA0 */ => { self.and(self.registers.read_byte(REG_B)); }
+
+      Opcode::AndC /* 0xA1 */ => { self.and(self.registers.read_byte(REG_C)); }
+
+      Opcode::AndD /* 0xA2 */ => { self.and(self.registers.read_byte(REG_D)); }
+
+      Opcode::AndE /* 0xA3 */ => { self.and(self.registers.read_byte(REG_E)); }
+
+      Opcode::AndH /* 0xA4 */ => { self.and(self.registers.read_byte(REG_H)); }
+
+      Opcode::AndL /* 0xA5 */ => { self.and(self.registers.read_byte(REG_L)); }
+
+      Opcode::AndAddrHl /* 0xA6 */ => {
+        let value = self.read_address(REG_HL);
+
+        self.and(value);
+      }
+
+      Opcode::AndA /* 0xA7 */ => { self.and(self.registers.read_byte(REG_A)); }
+
+      Opcode::AndImm /* 0xE6 */ => {
+        let value = self.read_immediate_byte();
+
+        self.and(value);
+      }
+
+      Opcode::XorB /* 0xA8 */ => { self.xor(self.registers.read_byte(REG_B)); }
+
+      Opcode::XorC /* 0xA9 */ => { self.xor(self.registers.read_byte(REG_C)); }
+
+      Opcode::XorD /* 0xAA */ => { self.xor(self.registers.read_byte(REG_D)); }
+
+      Opcode::XorE /* 0xAB */ => { self.xor(self.registers.read_byte(REG_E)); }
+
+      Opcode::XorH /* 0xAC */ => { self.xor(self.registers.read_byte(REG_H)); }
+
+      Opcode::XorL /* 0xAD */ => { self.xor(self.registers.read_byte(REG_L)); }
+
+      Opcode::XorAddrHl /* 0xAE */ => {
+        let value = self.read_address(REG_HL);
 
-        self.registers.set_flag(ZERO_FLAG, result == 0);
-        self.registers.write_byte(REG_A, result);
+        self.xor(value);
       }
 
+      Opcode::XorA /* 0xAF */ => { self.xor(self.registers.read_byte(REG_A)); }
+
+      Opcode::XorImm /* 0xEE */ => {
+        let value = self.read_immediate_byte();
+
+        self.xor(value);
+      }
+
+      Opcode::OrB /* 0xB0 */ => { self.or(self.registers.read_byte(REG_B)); }
+
+      Opcode::OrC /* 0xB1 */ => { self.or(self.registers.read_byte(REG_C)); }
+
+      Opcode::OrD /* 0xB2 */ => { self.or(self.registers.read_byte(REG_D)); }
+
+      Opcode::OrE /* 0xB3 */ => { self.or(self.registers.read_byte(REG_E)); }
+
+      Opcode::OrH /* 0xB4 */ => { self.or(self.registers.read_byte(REG_H)); }
+
+      Opcode::OrL /* 0xB5 */ => { self.or(self.registers.read_byte(REG_L)); }
+
+      Opcode::OrAddrHl /* 0xB6 */ => {
+        let value = self.read_address(REG_HL);
+
+        self.or(value);
+      }
+
+      Opcode::OrA /* 0xB7 */ => { self.or(self.registers.read_byte(REG_A)); }
+
+      Opcode::OrImm /* 0xF6 */ => {
+        let value = self.read_immediate_byte();
+
+        self.or(value);
+      }
+
+      Opcode::CompareB /* 0xB8 */ => { self.compare(self.registers.read_byte(REG_B)); }
+
+      Opcode::CompareC /* 0xB9 */ => { self.compare(self.registers.read_byte(REG_C)); }
+
+      Opcode::CompareD /* 0xBA */ => { self.compare(self.registers.read_byte(REG_D)); }
+
+      Opcode::CompareE /* 0xBB */ => { self.compare(self.registers.read_byte(REG_E)); }
+
+      Opcode::CompareH /* 0xBC */ => { self.compare(self.registers.read_byte(REG_H)); }
+
+      Opcode::CompareL /* 0xBD */ => { self.compare(self.registers.read_byte(REG_L)); }
+
+      Opcode::CompareAddrHl /* 0xBE */ => {
+        let value = self.read_address(REG_HL);
+
+        self.compare(value);
+      }
+
+      Opcode::CompareA /* 0xBF */ => { self.compare(self.registers.read_byte(REG_A)); }
+
       Opcode::CompareImm /* 0xFE */ => {
-        let a = self.registers.read_byte(REG_A);
         let value = self.read_immediate_byte();
-        let _ = self.subtract(a, value);
+
+        self.compare(value);
+      }
+
+      Opcode::DecimalAdjustA /* 0x27 */ => { self.decimal_adjust(); }
+
+      Opcode::ComplementA /* 0x2F */ => {
+        let a = self.registers.read_byte(REG_A);
+
+        self.registers.write_byte(REG_A, !a);
+        self.registers.set_flag(SUBTRACT_FLAG, true);
+        self.registers.set_flag(HALF_CARRY_FLAG, true);
+      }
+
+      Opcode::SetCarryFlag /* 0x37 */ => {
+        self.registers.set_flag(SUBTRACT_FLAG, false);
+        self.registers.set_flag(HALF_CARRY_FLAG, false);
+        self.registers.set_flag(CARRY_FLAG, true);
+      }
+
+      Opcode::ComplementCarryFlag /* 0x3F */ => {
+        let carry = self.registers.get_flag(CARRY_FLAG);
+
+        self.registers.set_flag(SUBTRACT_FLAG, false);
+        self.registers.set_flag(HALF_CARRY_FLAG, false);
+        self.registers.set_flag(CARRY_FLAG, !carry);
       }
 
       /*
@@ -278,9 +837,62 @@ impl<M: Memory> Processor<M> {
         ***************
       */
 
-      Opcode::IncrementDe /* 0x13 */ => { self.registers.increment_word(REG_DE); }
+      Opcode::IncrementBc /* 0x03 */ => {
+        self.registers.increment_word(REG_BC);
+        self.tick();
+      }
+
+      Opcode::IncrementDe /* 0x13 */ => {
+        self.registers.increment_word(REG_DE);
+        self.tick();
+      }
+
+      Opcode::IncrementHl /* 0x23 */ => {
+        self.registers.increment_word(REG_HL);
+        self.tick();
+      }
+
+      Opcode::IncrementSp /* 0x33 */ => {
+        self.registers.increment_stack_pointer(1);
+        self.tick();
+      }
+
+      Opcode::DecrementBc /* 0x0B */ => {
+        self.registers.decrement_word(REG_BC);
+        self.tick();
+      }
+
+      Opcode::DecrementDe /* 0x1B */ => {
+        self.registers.decrement_word(REG_DE);
+        self.tick();
+      }
+
+      Opcode::DecrementHl /* 0x2B */ => {
+        self.registers.decrement_word(REG_HL);
+        self.tick();
+      }
+
+      Opcode::DecrementSp /* 0x3B */ => {
+        self.registers.decrement_stack_pointer(1);
+        self.tick();
+      }
+
+      Opcode::AddBcToHl /* 0x09 */ => { self.add_to_hl(self.registers.read_word(REG_BC)); }
+
+      Opcode::AddDeToHl /* 0x19 */ => { self.add_to_hl(self.registers.read_word(REG_DE)); }
+
+      Opcode::AddHlToHl /* 0x29 */ => { self.add_to_hl(self.registers.read_word(REG_HL)); }
+
+      Opcode::AddSpToHl /* 0x39 */ => { self.add_to_hl(self.registers.get_stack_pointer()); }
 
-      Opcode::IncrementHl /* 0x23 */ => { self.registers.increment_word(REG_HL); }
+      Opcode::AddImmToSp /* 0xE8 */ => {
+        let value = self.read_immediate_byte();
+        let result = self.add_to_stack_pointer(value);
+
+        self.registers.set_stack_pointer(result);
+        self.tick();
+        self.tick();
+      }
 
       /*
         **********************
@@ -288,47 +900,110 @@ impl<M: Memory> Processor<M> {
         **********************
       */
 
+      Opcode::RotateLeftCircularA /* 0x07 */ => {
+        let a = self.registers.read_byte(REG_A);
+        let value = self.shift_left(a, (a & 0x80) != 0);
+
+        self.registers.write_byte(REG_A, value);
+        self.registers.set_flag(ZERO_FLAG, false);
+      }
+
+      Opcode::RotateRightCircularA /* 0x0F */ => {
+        let a = self.registers.read_byte(REG_A);
+        let value = self.shift_right(a, (a & 0x01) != 0);
+
+        self.registers.write_byte(REG_A, value);
+        self.registers.set_flag(ZERO_FLAG, false);
+      }
+
       Opcode::RotateLeftA /* 0x17 */ => {
         let a = self.registers.read_byte(REG_A);
         let carry = self.registers.get_flag(CARRY_FLAG);
         let value = self.shift_left(a, carry);
 
         self.registers.write_byte(REG_A, value);
+        self.registers.set_flag(ZERO_FLAG, false);
+      }
+
+      Opcode::RotateRightA /* 0x1F */ => {
+        let a = self.registers.read_byte(REG_A);
+        let carry = self.registers.get_flag(CARRY_FLAG);
+        let value = self.shift_right(a, carry);
+
+        self.registers.write_byte(REG_A, value);
+        self.registers.set_flag(ZERO_FLAG, false);
       }
     }
   }
 
   fn execute_special_instruction(&mut self, special_instruction: SpecialInstruction) {
-    match special_instruction.opcode() {
+    let operand = special_instruction.operand();
+    let value = self.read_operand(operand);
+
+    let result = match special_instruction.opcode() {
       /*
         **********************
         * 8-bit Shift/Rotate *
         **********************
       */
 
-      SpecialOpcode::RotateLeftC /* 0x11 */ => {
-        let c = self.registers.read_byte(REG_C);
+      SpecialOpcode::RotateLeftCircular => { self.shift_left(value, (value & 0x80) != 0) }
+
+      SpecialOpcode::RotateRightCircular => { self.shift_right(value, (value & 0x01) != 0) }
+
+      SpecialOpcode::RotateLeft => {
+        let carry = self.registers.get_flag(CARRY_FLAG);
+
+        self.shift_left(value, carry)
+      }
+
+      SpecialOpcode::RotateRight => {
         let carry = self.registers.get_flag(CARRY_FLAG);
-        let value = self.shift_left(c, carry);
 
-        self.registers.write_byte(REG_C, value);
+        self.shift_right(value, carry)
       }
 
-      SpecialOpcode::Bit7H /* 0x7C */ => {
-        let h = self.registers.read_byte(REG_H);
+      SpecialOpcode::ShiftLeftArithmetic => { self.shift_left(value, false) }
 
-        self.registers.set_flag(ZERO_FLAG, (h & 0x80) == 0);
+      SpecialOpcode::ShiftRightArithmetic => { self.shift_right(value, (value & 0x80) != 0) }
+
+      SpecialOpcode::Swap => {
+        let result = value.rotate_left(4);
+
+        self.set_logic_flags(result, false);
+
+        result
+      }
+
+      SpecialOpcode::ShiftRightLogical => { self.shift_right(value, false) }
+
+      /*
+        ******************
+        * Bit Operations *
+        ******************
+      */
+
+      SpecialOpcode::Bit(bit) => {
+        self.registers.set_flag(ZERO_FLAG, (value & (1 << bit)) == 0);
         self.registers.set_flag(SUBTRACT_FLAG, false);
         self.registers.set_flag(HALF_CARRY_FLAG, true);
+
+        // BIT only reads its operand, so there is nothing to write back
+        return;
       }
-    }
+
+      SpecialOpcode::Reset(bit) => { value & !(1 << bit) }
+
+      SpecialOpcode::Set(bit) => { value | (1 << bit) }
+    };
+
+    self.write_operand(operand, result);
   }
 
   fn read_special_instruction(&mut self) -> SpecialInstruction {
     let pc = self.registers.get_program_counter();
-    let address = M::B::from(pc);
 
-    let special_instruction = SpecialInstruction(self.memory.read_byte(address));
+    let special_instruction = SpecialInstruction(self.read_memory(pc));
 
     println!("PC: {:#06x}: {:?}", pc, special_instruction);
 
@@ -337,10 +1012,125 @@ impl<M: Memory> Processor<M> {
     special_instruction
   }
 
+  fn pending_interrupt(&self) -> Option<Interrupt> {
+    let enable = self.memory.read_byte(M::B::from(INTERRUPT_ENABLE_REG));
+    let flag = self.memory.read_byte(M::B::from(INTERRUPT_FLAG_REG));
+
+    Interrupt::from_bits(enable & flag)
+  }
+
+  fn service_interrupt(&mut self) {
+    let mut pc = self.registers.get_program_counter();
+
+    // An interrupt straight after a bugged HALT returns to the HALT itself
+    if self.halt_bug {
+      self.halt_bug = false;
+      pc = pc.wrapping_sub(1);
+    }
+
+    self.registers.set_interrupt_master_enable(false);
+
+    self.tick();
+    self.tick();
+
+    self.registers.decrement_stack_pointer(1);
+    let sp = self.registers.get_stack_pointer();
+    self.write_memory(sp, (pc >> 8) as u8);
+
+    // The interrupt is chosen after the high byte is pushed, which may have overwritten IE
+    let interrupt = self.pending_interrupt();
+
+    self.registers.decrement_stack_pointer(1);
+    let sp = self.registers.get_stack_pointer();
+    self.write_memory(sp, (pc & 0xFF) as u8);
+
+    match interrupt {
+      Some(interrupt) => {
+        let flag = self.memory.read_byte(M::B::from(INTERRUPT_FLAG_REG));
+
+        self.memory.write_byte(M::B::from(INTERRUPT_FLAG_REG), flag & !interrupt.mask());
+        self.jump(interrupt.vector());
+      }
+
+      // Cancelled dispatch lands on 0x0000 instead
+      None => self.jump(0x0000),
+    }
+  }
+
+  fn button_held(&self) -> bool {
+    let joypad = self.memory.read_byte(M::B::from(JOYPAD_REG));
+
+    // Buttons on the selected lines read as 0 while pressed
+    (joypad & 0x0F) != 0x0F
+  }
+
+  fn stop(&mut self) {
+    if self.button_held() {
+      // STOP is a two byte opcode that enters HALT, unless an interrupt is
+      // already pending in which case it behaves as a single byte NOP
+      if self.pending_interrupt().is_none() {
+        self.registers.increment_program_counter(1);
+        self.state = State::Halted;
+      }
+
+      return;
+    }
+
+    self.registers.increment_program_counter(1);
+    self.memory.write_byte(M::B::from(DIVIDER_REG), 0);
+
+    if self.memory.switch_speed() {
+      for _ in 0..SPEED_SWITCH_MACHINE_CYCLES {
+        self.tick();
+      }
+    } else {
+      self.state = State::Stopped;
+    }
+  }
+
+  // Advances the processor and everything attached to its memory by one M-cycle
+  fn tick(&mut self) {
+    self.cycles += CYCLES_PER_MACHINE_CYCLE as u64;
+    self.memory.tick(CYCLES_PER_MACHINE_CYCLE);
+  }
+
+  fn read_memory(&mut self, address: u16) -> u8 {
+    self.tick();
+    self.memory.read_byte(M::B::from(address))
+  }
+
+  fn write_memory(&mut self, address: u16, value: u8) {
+    self.tick();
+    self.memory.write_byte(M::B::from(address), value);
+  }
+
+  fn read_address(&mut self, register: WordRegister) -> u8 {
+    let address = self.registers.read_word(register);
+
+    self.read_memory(address)
+  }
+
+  fn read_operand(&mut self, operand: Operand) -> u8 {
+    match operand {
+      Operand::Register(register) => self.registers.read_byte(register),
+      Operand::AddrHl => self.read_address(REG_HL),
+    }
+  }
+
+  fn write_operand(&mut self, operand: Operand, value: u8) {
+    match operand {
+      Operand::Register(register) => self.registers.write_byte(register, value),
+      Operand::AddrHl => {
+        let hl = self.registers.read_word(REG_HL);
+
+        self.write_memory(hl, value);
+      }
+    }
+  }
+
   fn read_immediate_byte(&mut self) -> u8 {
     let pc = self.registers.get_program_counter();
-    let address = M::B::from(pc);
-    let immediate = self.memory.read_byte(address);
+    let immediate = self.read_memory(pc);
 
     self.registers.increment_program_counter(1);
 
@@ -348,29 +1138,47 @@ impl<M: Memory> Processor<M> {
   }
 
   fn read_immediate_word(&mut self) -> u16 {
-    let pc = self.registers.get_program_counter();
-    let address = M::W::from(pc);
-    let immediate = self.memory.read_word(address);
-
-    self.registers.increment_program_counter(2);
+    let low = self.read_immediate_byte() as u16;
+    let high = self.read_immediate_byte() as u16;
 
-    immediate
+    high << 8 | low
   }
 
   fn stack_push(&mut self, value: u16) {
+    // The stack pointer is decremented in an internal cycle before the writes
+    self.tick();
+
+    self.registers.decrement_stack_pointer(1);
     let sp = self.registers.get_stack_pointer();
-    let address = M::W::from(sp - 1);
+    self.write_memory(sp, (value >> 8) as u8);
 
-    self.registers.decrement_stack_pointer(2);
-    self.memory.write_word(address, value);
+    self.registers.decrement_stack_pointer(1);
+    let sp = self.registers.get_stack_pointer();
+    self.write_memory(sp, (value & 0xFF) as u8);
   }
 
   fn stack_pop(&mut self) -> u16 {
     let sp = self.registers.get_stack_pointer();
-    let address = M::W::from(sp + 1);
+    let low = self.read_memory(sp) as u16;
+    self.registers.increment_stack_pointer(1);
+
+    let sp = self.registers.get_stack_pointer();
+    let high = self.read_memory(sp) as u16;
+    self.registers.increment_stack_pointer(1);
+
+    high << 8 | low
+  }
+
+  fn push(&mut self, register: WordRegister) {
+    let value = self.registers.read_word(register);
 
-    self.registers.increment_stack_pointer(2);
-    self.memory.read_word(address)
+    self.stack_push(value);
+  }
+
+  fn pop(&mut self, register: WordRegister) {
+    let value = self.stack_pop();
+
+    self.registers.write_word(register, value);
   }
 
   fn shift_left(&mut self, value: u8, lsb: bool) -> u8 {
@@ -380,22 +1188,142 @@ impl<M: Memory> Processor<M> {
     if lsb { result |= 1; }
 
     self.registers.set_flag(ZERO_FLAG, result == 0);
+    self.registers.set_flag(SUBTRACT_FLAG, false);
+    self.registers.set_flag(HALF_CARRY_FLAG, false);
     self.registers.set_flag(CARRY_FLAG, carry);
 
     result
   }
 
-  fn subtract(&mut self, a: u8, b: u8) -> u8 {
-    let result = a.wrapping_sub(b);
+  fn shift_right(&mut self, value: u8, msb: bool) -> u8 {
+    let carry = (value & 0x01) != 0;
+    let mut result = value >> 1;
+
+    if msb { result |= 0x80; }
+
+    self.registers.set_flag(ZERO_FLAG, result == 0);
+    self.registers.set_flag(SUBTRACT_FLAG, false);
+    self.registers.set_flag(HALF_CARRY_FLAG, false);
+    self.registers.set_flag(CARRY_FLAG, carry);
+
+    result
+  }
+
+  fn add(&mut self, value: u8, use_carry: bool) {
+    let a = self.registers.read_byte(REG_A);
+    let carry = (use_carry && self.registers.get_flag(CARRY_FLAG)) as u8;
+    let result = a.wrapping_add(value).wrapping_add(carry);
+
+    self.registers.set_flag(ZERO_FLAG, result == 0);
+    self.registers.set_flag(SUBTRACT_FLAG, false);
+    self.registers.set_flag(HALF_CARRY_FLAG, (a & 0xF) + (value & 0xF) + carry > 0xF);
+    self.registers.set_flag(CARRY_FLAG, a as u16 + value as u16 + carry as u16 > 0xFF);
+
+    self.registers.write_byte(REG_A, result);
+  }
+
+  fn subtract(&mut self, value: u8, use_carry: bool) {
+    let a = self.registers.read_byte(REG_A);
+    let result = self.difference(a, value, use_carry);
+
+    self.registers.write_byte(REG_A, result);
+  }
+
+  fn compare(&mut self, value: u8) {
+    let a = self.registers.read_byte(REG_A);
+    let _ = self.difference(a, value, false);
+  }
+
+  fn difference(&mut self, a: u8, b: u8, use_carry: bool) -> u8 {
+    let carry = (use_carry && self.registers.get_flag(CARRY_FLAG)) as u8;
+    let result = a.wrapping_sub(b).wrapping_sub(carry);
 
     self.registers.set_flag(SUBTRACT_FLAG, true);
-    self.registers.set_flag(HALF_CARRY_FLAG, (a & 0xF) < (b & 0xF));
-    self.registers.set_flag(CARRY_FLAG, (a & 0xFF) < (b & 0xff));
+    self.registers.set_flag(HALF_CARRY_FLAG, (a & 0xF) < (b & 0xF) + carry);
+    self.registers.set_flag(CARRY_FLAG, (a as u16) < b as u16 + carry as u16);
     self.registers.set_flag(ZERO_FLAG, result == 0);
 
     result
   }
 
+  fn and(&mut self, value: u8) {
+    let result = self.registers.read_byte(REG_A) & value;
+
+    self.set_logic_flags(result, true);
+    self.registers.write_byte(REG_A, result);
+  }
+
+  fn xor(&mut self, value: u8) {
+    let result = self.registers.read_byte(REG_A) ^ value;
+
+    self.set_logic_flags(result, false);
+    self.registers.write_byte(REG_A, result);
+  }
+
+  fn or(&mut self, value: u8) {
+    let result = self.registers.read_byte(REG_A) | value;
+
+    self.set_logic_flags(result, false);
+    self.registers.write_byte(REG_A, result);
+  }
+
+  fn set_logic_flags(&mut self, result: u8, half_carry: bool) {
+    self.registers.set_flag(ZERO_FLAG, result == 0);
+    self.registers.set_flag(SUBTRACT_FLAG, false);
+    self.registers.set_flag(HALF_CARRY_FLAG, half_carry);
+    self.registers.set_flag(CARRY_FLAG, false);
+  }
+
+  fn decimal_adjust(&mut self) {
+    let a = self.registers.read_byte(REG_A);
+    let subtract = self.registers.get_flag(SUBTRACT_FLAG);
+    let half_carry = self.registers.get_flag(HALF_CARRY_FLAG);
+    let mut carry = self.registers.get_flag(CARRY_FLAG);
+    let mut adjustment = 0;
+
+    if half_carry || (!subtract && (a & 0xF) > 0x9) {
+      adjustment |= 0x06;
+    }
+
+    if carry || (!subtract && a > 0x99) {
+      adjustment |= 0x60;
+      carry = true;
+    }
+
+    let result = if subtract { a.wrapping_sub(adjustment) } else { a.wrapping_add(adjustment) };
+
+    self.registers.set_flag(ZERO_FLAG, result == 0);
+    self.registers.set_flag(HALF_CARRY_FLAG, false);
+    self.registers.set_flag(CARRY_FLAG, carry);
+
+    self.registers.write_byte(REG_A, result);
+  }
+
+  fn add_to_hl(&mut self, value: u16) {
+    let hl = self.registers.read_word(REG_HL);
+    let result = hl.wrapping_add(value);
+
+    self.registers.set_flag(SUBTRACT_FLAG, false);
+    self.registers.set_flag(HALF_CARRY_FLAG, (hl & 0x0FFF) + (value & 0x0FFF) > 0x0FFF);
+    self.registers.set_flag(CARRY_FLAG, hl as u32 + value as u32 > 0xFFFF);
+
+    self.registers.write_word(REG_HL, result);
+    self.tick();
+  }
+
+  fn add_to_stack_pointer(&mut self, offset: u8) -> u16 {
+    let sp = self.registers.get_stack_pointer();
+    let value = (offset as i8) as u16;
+
+    // Flags are computed from the unsigned addition of the low byte
+    self.registers.set_flag(ZERO_FLAG, false);
+    self.registers.set_flag(SUBTRACT_FLAG, false);
+    self.registers.set_flag(HALF_CARRY_FLAG, (sp & 0xF) + (value & 0xF) > 0xF);
+    self.registers.set_flag(CARRY_FLAG, (sp & 0xFF) + (value & 0xFF) > 0xFF);
+
+    sp.wrapping_add(value)
+  }
+
   fn load_immediate_byte(&mut self, register: ByteRegister) {
     let value = self.read_immediate_byte();
 
@@ -409,31 +1337,60 @@ impl<M: Memory> Processor<M> {
   }
 
   fn transfer_to_immediate_address(&mut self, from: ByteRegister) {
-    let address = M::B::from(self.read_immediate_word());
+    let address = self.read_immediate_word();
     let value = self.registers.read_byte(from);
 
-    self.memory.write_byte(address, value);
+    self.write_memory(address, value);
+  }
+
+  fn transfer_from_immediate_address(&mut self, to: ByteRegister) {
+    let address = self.read_immediate_word();
+    let value = self.read_memory(address);
+
+    self.registers.write_byte(to, value);
   }
 
   fn transfer_to_address(&mut self, from: ByteRegister, to: WordRegister) {
     let value = self.registers.read_byte(from);
-    let address = M::B::from(self.registers.read_word(to));
+    let address = self.registers.read_word(to);
 
-    self.memory.write_byte(address, value);
+    self.write_memory(address, value);
   }
 
   fn transfer_from_address(&mut self, from: WordRegister, to: ByteRegister) {
-    let address = M::B::from(self.registers.read_word(from));
-    let value = self.memory.read_byte(address);
+    let value = self.read_address(from);
 
     self.registers.write_byte(to, value);
   }
 
+  fn call(&mut self, address: u16) {
+    let pc = self.registers.get_program_counter();
+
+    self.stack_push(pc);
+    self.registers.set_program_counter(address);
+  }
+
+  fn ret(&mut self) {
+    let value = self.stack_pop();
+
+    self.jump(value);
+  }
+
+  fn jump(&mut self, address: u16) {
+    self.registers.set_program_counter(address);
+    self.tick();
+  }
+
+  fn jump_relative(&mut self, offset: u8) {
+    self.registers.increment_program_counter((offset as i8) as i16);
+    self.tick();
+  }
+
   fn jump_conditionally(&mut self, flag: u8, condition: bool) {
     let value = self.read_immediate_word();
 
     if self.registers.get_flag(flag) == condition {
-      self.registers.set_program_counter(value);
+      self.jump(value);
     }
   }
 
@@ -441,7 +1398,24 @@ impl<M: Memory> Processor<M> {
     let offset = self.read_immediate_byte();
 
     if self.registers.get_flag(flag) == condition {
-      self.registers.increment_program_counter((offset as i8) as i16);
+      self.jump_relative(offset);
+    }
+  }
+
+  fn call_conditionally(&mut self, flag: u8, condition: bool) {
+    let value = self.read_immediate_word();
+
+    if self.registers.get_flag(flag) == condition {
+      self.call(value);
+    }
+  }
+
+  fn return_conditionally(&mut self, flag: u8, condition: bool) {
+    // Evaluating the condition takes an extra internal cycle
+    self.tick();
+
+    if self.registers.get_flag(flag) == condition {
+      self.ret();
     }
   }
 }
@@ -3,9 +3,11 @@ enum_from_primitive! {
   pub enum Opcode {
     // Control
     NoOp = 0x00,
-    // Stop = 0x10,
-    // Halt = 0x76,
+    Stop = 0x10,
+    Halt = 0x76,
     Special = 0xCB,
+    DisableInterrupts = 0xF3,
+    EnableInterrupts = 0xFB,
 
     // Flow Control
     Jump = 0xC3,
@@ -13,19 +15,96 @@ enum_from_primitive! {
     JumpZero = 0xCA,
     JumpNonCarry = 0xD2,
     JumpCarry = 0xDA,
+    JumpHl = 0xE9,
     JumpRelative = 0x18,
     JumpRelativeNonZero = 0x20,
     JumpRelativeZero = 0x28,
     JumpRelativeNonCarry = 0x30,
     JumpRelativeCarry = 0x38,
     Return = 0xC9,
+    ReturnNonZero = 0xC0,
+    ReturnZero = 0xC8,
+    ReturnNonCarry = 0xD0,
+    ReturnCarry = 0xD8,
+    ReturnFromInterrupt = 0xD9,
     CallImmAddr = 0xCD,
+    CallNonZero = 0xC4,
+    CallZero = 0xCC,
+    CallNonCarry = 0xD4,
+    CallCarry = 0xDC,
+    Restart00 = 0xC7,
+    Restart08 = 0xCF,
+    Restart10 = 0xD7,
+    Restart18 = 0xDF,
+    Restart20 = 0xE7,
+    Restart28 = 0xEF,
+    Restart30 = 0xF7,
+    Restart38 = 0xFF,
 
     // 8-bit Load
+    LoadBIntoB = 0x40,
+    LoadCIntoB = 0x41,
+    LoadDIntoB = 0x42,
+    LoadEIntoB = 0x43,
+    LoadHIntoB = 0x44,
+    LoadLIntoB = 0x45,
+    LoadAddrHlIntoB = 0x46,
+    LoadAIntoB = 0x47,
+    LoadBIntoC = 0x48,
+    LoadCIntoC = 0x49,
+    LoadDIntoC = 0x4A,
+    LoadEIntoC = 0x4B,
+    LoadHIntoC = 0x4C,
+    LoadLIntoC = 0x4D,
+    LoadAddrHlIntoC = 0x4E,
     LoadAIntoC = 0x4f,
+    LoadBIntoD = 0x50,
+    LoadCIntoD = 0x51,
+    LoadDIntoD = 0x52,
+    LoadEIntoD = 0x53,
+    LoadHIntoD = 0x54,
+    LoadLIntoD = 0x55,
+    LoadAddrHlIntoD = 0x56,
     LoadAIntoD = 0x57,
+    LoadBIntoE = 0x58,
+    LoadCIntoE = 0x59,
+    LoadDIntoE = 0x5A,
+    LoadEIntoE = 0x5B,
+    LoadHIntoE = 0x5C,
+    LoadLIntoE = 0x5D,
+    LoadAddrHlIntoE = 0x5E,
+    LoadAIntoE = 0x5F,
+    LoadBIntoH = 0x60,
+    LoadCIntoH = 0x61,
+    LoadDIntoH = 0x62,
+    LoadEIntoH = 0x63,
+    LoadHIntoH = 0x64,
+    LoadLIntoH = 0x65,
+    LoadAddrHlIntoH = 0x66,
     LoadAIntoH = 0x67,
+    LoadBIntoL = 0x68,
+    LoadCIntoL = 0x69,
+    LoadDIntoL = 0x6A,
+    LoadEIntoL = 0x6B,
+    LoadHIntoL = 0x6C,
+    LoadLIntoL = 0x6D,
+    LoadAddrHlIntoL = 0x6E,
+    LoadAIntoL = 0x6F,
+    LoadBIntoAddrHl = 0x70,
+    LoadCIntoAddrHl = 0x71,
+    LoadDIntoAddrHl = 0x72,
+    LoadEIntoAddrHl = 0x73,
+    LoadHIntoAddrHl = 0x74,
+    LoadLIntoAddrHl = 0x75,
+    LoadAIntoAddrHl = 0x77,
+    LoadBIntoA = 0x78,
+    LoadCIntoA = 0x79,
+    LoadDIntoA = 0x7A,
     LoadEIntoA = 0x7B,
+    LoadHIntoA = 0x7C,
+    LoadLIntoA = 0x7D,
+    LoadAddrHlIntoA = 0x7E,
+    LoadAIntoA = 0x7F,
     LoadImmIntoB = 0x06,
     LoadImmIntoC = 0x0E,
     LoadImmIntoD = 0x16,
@@ -35,12 +114,13 @@ enum_from_primitive! {
     LoadImmIntoA = 0x3E,
     LoadImmIntoAddrHl = 0x36,
     LoadAIntoAddrC = 0xE2,
+    LoadAddrCIntoA = 0xF2,
     LoadAIntoAddrBc = 0x02,
     LoadAIntoAddrDe = 0x12,
-    LoadAIntoAddrHl = 0x77,
     LoadAddrBcIntoA = 0x0A,
     LoadAddrDeIntoA = 0x1A,
     LoadAIntoImmAddr = 0xEA,
+    LoadImmAddrIntoA = 0xFA,
     LoadAIntoAddrHlAndInc = 0x22,
     LoadAddrHLIntoAAndInc = 0x2A,
     LoadAIntoAddrHlAndDec = 0x32,
@@ -54,32 +134,151 @@ enum_from_primitive! {
     LoadImmIntoHl = 0x21,
     LoadImmIntoSp = 0x31,
     LoadSpIntoImmAddr = 0x08,
+    LoadHlIntoSp = 0xF9,
+    LoadSpPlusImmIntoHl = 0xF8,
     PopBc = 0xC1,
+    PopDe = 0xD1,
+    PopHl = 0xE1,
+    PopAf = 0xF1,
     PushBc = 0xC5,
+    PushDe = 0xD5,
+    PushHl = 0xE5,
+    PushAf = 0xF5,
 
     // 8-bit Math
     IncrementB = 0x04,
     IncrementC = 0x0C,
+    IncrementD = 0x14,
+    IncrementE = 0x1C,
+    IncrementH = 0x24,
+    IncrementL = 0x2C,
+    IncrementAddrHl = 0x34,
+    IncrementA = 0x3C,
     DecrementB = 0x05,
     DecrementC = 0x0D,
+    DecrementD = 0x15,
+    DecrementE = 0x1D,
+    DecrementH = 0x25,
+    DecrementL = 0x2D,
+    DecrementAddrHl = 0x35,
     DecrementA = 0x3D,
+    AddB = 0x80,
+    AddC = 0x81,
+    AddD = 0x82,
+    AddE = 0x83,
+    AddH = 0x84,
+    AddL = 0x85,
+    AddAddrHl = 0x86,
+    AddA = 0x87,
+    AddImm = 0xC6,
+    AddWithCarryB = 0x88,
+    AddWithCarryC = 0x89,
+    AddWithCarryD = 0x8A,
+    AddWithCarryE = 0x8B,
+    AddWithCarryH = 0x8C,
+    AddWithCarryL = 0x8D,
+    AddWithCarryAddrHl = 0x8E,
+    AddWithCarryA = 0x8F,
+    AddWithCarryImm = 0xCE,
+    SubtractB = 0x90,
+    SubtractC = 0x91,
+    SubtractD = 0x92,
+    SubtractE = 0x93,
+    SubtractH = 0x94,
     SubtractL = 0x95,
+    SubtractAddrHl = 0x96,
+    SubtractA = 0x97,
+    SubtractImm = 0xD6,
+    SubtractWithCarryB = 0x98,
+    SubtractWithCarryC = 0x99,
+    SubtractWithCarryD = 0x9A,
+    SubtractWithCarryE = 0x9B,
+    SubtractWithCarryH = 0x9C,
+    SubtractWithCarryL = 0x9D,
+    SubtractWithCarryAddrHl = 0x9E,
+    SubtractWithCarryA = 0x9F,
+    SubtractWithCarryImm = 0xDE,
+    AndB = 0xA0,
+    AndC = 0xA1,
+    AndD = 0xA2,
+    AndE = 0xA3,
+    AndH = 0xA4,
+    AndL = 0xA5,
+    AndAddrHl = 0xA6,
+    AndA = 0xA7,
+    AndImm = 0xE6,
+    XorB = 0xA8,
+    XorC = 0xA9,
+    XorD = 0xAA,
+    XorE = 0xAB,
+    XorH = 0xAC,
+    XorL = 0xAD,
+    XorAddrHl = 0xAE,
     XorA = 0xAF,
+    XorImm = 0xEE,
+    OrB = 0xB0,
+    OrC = 0xB1,
+    OrD = 0xB2,
+    OrE = 0xB3,
+    OrH = 0xB4,
+    OrL = 0xB5,
+    OrAddrHl = 0xB6,
+    OrA = 0xB7,
+    OrImm = 0xF6,
+    CompareB = 0xB8,
+    CompareC = 0xB9,
+    CompareD = 0xBA,
+    CompareE = 0xBB,
+    CompareH = 0xBC,
+    CompareL = 0xBD,
+    CompareAddrHl = 0xBE,
+    CompareA = 0xBF,
     CompareImm = 0xFE,
+    DecimalAdjustA = 0x27,
+    ComplementA = 0x2F,
+    SetCarryFlag = 0x37,
+    ComplementCarryFlag = 0x3F,
 
     // 16-bit Math
+    IncrementBc = 0x03,
     IncrementDe = 0x13,
     IncrementHl = 0x23,
+    IncrementSp = 0x33,
+    DecrementBc = 0x0B,
+    DecrementDe = 0x1B,
+    DecrementHl = 0x2B,
+    DecrementSp = 0x3B,
+    AddBcToHl = 0x09,
+    AddDeToHl = 0x19,
+    AddHlToHl = 0x29,
+    AddSpToHl = 0x39,
+    AddImmToSp = 0xE8,
 
     // 8-bit Shift/Rotate
+    RotateLeftCircularA = 0x07,
+    RotateRightCircularA = 0x0F,
     RotateLeftA = 0x17,
+    RotateRightA = 0x1F,
   }
 }
 
-enum_from_primitive! {
-  #[derive(Debug)]
-  pub enum SpecialOpcode {
-    RotateLeftC = 0x11,
-    Bit7H = 0x7C,
-  }
+// Prefixed opcodes are decoded from their bit fields rather than enumerated:
+// bits 7-6 select the group, bits 5-3 the operation or bit index and bits 2-0
+// the operand (see `SpecialInstruction`).
+#[derive(Debug)]
+pub enum SpecialOpcode {
+  // 8-bit Shift/Rotate
+  RotateLeftCircular,
+  RotateRightCircular,
+  RotateLeft,
+  RotateRight,
+  ShiftLeftArithmetic,
+  ShiftRightArithmetic,
+  Swap,
+  ShiftRightLogical,
+
+  // Bit Operations
+  Bit(u8),
+  Reset(u8),
+  Set(u8),
 }
@@ -2,12 +2,23 @@ use std::ops;
 use std::fmt;
 
 use super::super::memory::Memory;
+use super::super::model::PostBootRegisters;
 
 const NUM_GPR: usize = 8;
 
+const BYTE_REGISTERS: [&str; NUM_GPR] = [
+  "A", "F", "B", "C", "D", "E", "H", "L"
+];
+
 #[derive(Copy, Clone)]
 pub struct ByteRegister(usize);
 
+impl fmt::Debug for ByteRegister {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}", BYTE_REGISTERS[self.0])
+  }
+}
+
 impl From<u16> for ByteRegister {
   fn from(scalar: u16) -> Self {
     ByteRegister(scalar as usize)
@@ -70,7 +81,6 @@ pub const REG_E: ByteRegister = ByteRegister(REG_D_INDEX + 1);
 pub const REG_H: ByteRegister = ByteRegister(REG_H_INDEX);
 pub const REG_L: ByteRegister = ByteRegister(REG_H_INDEX + 1);
 
-#[allow(dead_code)]
 pub const REG_AF: WordRegister = WordRegister(REG_A_INDEX);
 pub const REG_BC: WordRegister = WordRegister(REG_B_INDEX);
 pub const REG_DE: WordRegister = WordRegister(REG_D_INDEX);
@@ -85,6 +95,7 @@ pub struct Registers {
   value: [u8; NUM_GPR],
   sp: u16,
   pc: u16,
+  ime: bool, // Interrupt Master Enable
 }
 
 impl Registers {
@@ -93,9 +104,23 @@ impl Registers {
       value: [0; NUM_GPR],
       sp: 0,
       pc: 0,
+      ime: false,
     }
   }
 
+  pub fn post_boot(state: PostBootRegisters) -> Registers {
+    let mut registers = Registers::new();
+
+    registers.write_word(REG_AF, state.af);
+    registers.write_word(REG_BC, state.bc);
+    registers.write_word(REG_DE, state.de);
+    registers.write_word(REG_HL, state.hl);
+    registers.sp = state.sp;
+    registers.pc = state.pc;
+
+    registers
+  }
+
   pub fn get_stack_pointer(&self) -> u16 {
     self.sp
   }
@@ -105,11 +130,11 @@ impl Registers {
   }
 
   pub fn increment_stack_pointer(&mut self, value: i16) {
-    self.sp = (self.sp as i16 + value) as u16;
+    self.sp = self.sp.wrapping_add(value as u16);
   }
 
   pub fn decrement_stack_pointer(&mut self, value: i16) {
-    self.sp = (self.sp as i16 - value) as u16;
+    self.sp = self.sp.wrapping_sub(value as u16);
   }
 
   pub fn get_program_counter(&self) -> u16 {
@@ -121,7 +146,15 @@ impl Registers {
   }
 
   pub fn increment_program_counter(&mut self, value: i16) {
-    self.pc = (self.pc as i16 + value) as u16;
+    self.pc = self.pc.wrapping_add(value as u16);
+  }
+
+  pub fn get_interrupt_master_enable(&self) -> bool {
+    self.ime
+  }
+
+  pub fn set_interrupt_master_enable(&mut self, on: bool) {
+    self.ime = on;
   }
 
   pub fn get_flag(&mut self, flag: u8) -> bool {
@@ -142,25 +175,37 @@ impl Registers {
     self.write_byte(REG_F, f);
   }
 
-  pub fn increment_byte(&mut self, register: ByteRegister) {
-    let previous = self.read_byte(register);
+  pub fn increment(&mut self, previous: u8) -> u8 {
     let result = previous.wrapping_add(1);
 
     self.set_flag(ZERO_FLAG, result == 0);
     self.set_flag(SUBTRACT_FLAG, false);
     self.set_flag(HALF_CARRY_FLAG, ((previous & 0xF).wrapping_add(1)) & 0x10 != 0);
 
-    self.write_byte(register, result);
+    result
   }
 
-  pub fn decrement_byte(&mut self, register: ByteRegister) {
-    let previous = self.read_byte(register);
+  pub fn decrement(&mut self, previous: u8) -> u8 {
     let result = previous.wrapping_sub(1);
 
     self.set_flag(ZERO_FLAG, result == 0);
     self.set_flag(SUBTRACT_FLAG, true);
     self.set_flag(HALF_CARRY_FLAG, ((previous & 0xF).wrapping_sub(1)) & 0x10 != 0);
 
+    result
+  }
+
+  pub fn increment_byte(&mut self, register: ByteRegister) {
+    let previous = self.read_byte(register);
+    let result = self.increment(previous);
+
+    self.write_byte(register, result);
+  }
+
+  pub fn decrement_byte(&mut self, register: ByteRegister) {
+    let previous = self.read_byte(register);
+    let result = self.decrement(previous);
+
     self.write_byte(register, result);
   }
 
@@ -220,10 +265,7 @@ impl fmt::Debug for Cpu {
 
 impl fmt::Debug for Registers {
   fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-    // const BYTE_REGISTERS: [&'static str; NUM_GPR] = [
-    //   "A", "F", "B", "C", "D", "E", "H", "L"
-    // ];
-    const WORD_REGISTERS: [&'static str; NUM_GPR/2] = [
+    const WORD_REGISTERS: [&str; NUM_GPR/2] = [
       "AF", "BC", "DE", "HL"
     ];
 
@@ -236,16 +278,14 @@ impl fmt::Debug for Registers {
     //
     // try!(writeln!(f, ""));
 
-    for index in 0..NUM_GPR/2 {
+    for (index, name) in WORD_REGISTERS.iter().enumerate() {
       let value = self.read_word(WordRegister(index * 2));
-      try!(
-        writeln!(f, "  {name}: {value:#06x}", name = WORD_REGISTERS[index], value = value)
-      )
+      writeln!(f, "  {name}: {value:#06x}", name = name, value = value)?;
     }
 
-    try!(writeln!(f, "  SP: {:#06x}", self.sp));
-    try!(writeln!(f, "  PC: {:#06x}", self.pc));
+    writeln!(f, "  SP: {:#06x}", self.sp)?;
+    writeln!(f, "  PC: {:#06x}", self.pc)?;
 
-    writeln!(f, "")
+    writeln!(f)
   }
 }
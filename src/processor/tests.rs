@@ -0,0 +1,620 @@
+use super::*;
+use super::super::model::Model;
+
+const PROGRAM_START: u16 = 0x0100;
+const STACK_START: u16 = 0xFFFE;
+const DATA_ADDRESS: u16 = 0xC000;
+
+// 64 KiB of plain RAM, with nothing mapped into it
+struct FlatMemory {
+  bytes: Vec<u8>,
+}
+
+impl Memory for FlatMemory {
+  type B = u16;
+  type W = u16;
+
+  fn read_byte(&self, address: u16) -> u8 {
+    self.bytes[address as usize]
+  }
+
+  fn write_byte(&mut self, address: u16, value: u8) {
+    self.bytes[address as usize] = value;
+  }
+}
+
+fn processor(program: &[u8]) -> Processor<FlatMemory> {
+  let mut bytes = vec![0; 0x10000];
+  let start = PROGRAM_START as usize;
+
+  bytes[start..start + program.len()].copy_from_slice(program);
+
+  // No buttons held
+  bytes[JOYPAD_REG as usize] = 0xFF;
+
+  let mut registers = Registers::new();
+
+  registers.set_program_counter(PROGRAM_START);
+  registers.set_stack_pointer(STACK_START);
+
+  Processor::with_registers(registers, FlatMemory { bytes })
+}
+
+// Runs the first few instructions of the program
+fn run(program: &[u8], steps: usize) -> Processor<FlatMemory> {
+  let mut processor = processor(program);
+
+  for _ in 0..steps {
+    processor.step();
+  }
+
+  processor
+}
+
+fn flags(processor: &Processor<FlatMemory>) -> u8 {
+  processor.registers.read_byte(REG_F)
+}
+
+fn a(processor: &Processor<FlatMemory>) -> u8 {
+  processor.registers.read_byte(REG_A)
+}
+
+#[test]
+fn add_sets_every_flag_on_overflow_to_zero() {
+  // LD A,0x3A; ADD A,0xC6
+  let processor = run(&[0x3E, 0x3A, 0xC6, 0xC6], 2);
+
+  assert_eq!(a(&processor), 0x00);
+  assert_eq!(flags(&processor), ZERO_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn add_half_carry_only() {
+  // LD A,0x0F; ADD A,0x01
+  let processor = run(&[0x3E, 0x0F, 0xC6, 0x01], 2);
+
+  assert_eq!(a(&processor), 0x10);
+  assert_eq!(flags(&processor), HALF_CARRY_FLAG);
+}
+
+#[test]
+fn add_with_carry_includes_carry_in_both_flags() {
+  // SCF; LD A,0xE1; ADC A,0x0F
+  let processor = run(&[0x37, 0x3E, 0xE1, 0xCE, 0x0F], 3);
+
+  assert_eq!(a(&processor), 0xF1);
+  assert_eq!(flags(&processor), HALF_CARRY_FLAG);
+
+  // SCF; LD A,0xFF; ADC A,0x00
+  let processor = run(&[0x37, 0x3E, 0xFF, 0xCE, 0x00], 3);
+
+  assert_eq!(a(&processor), 0x00);
+  assert_eq!(flags(&processor), ZERO_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn subtract_flags() {
+  // LD A,0x3E; SUB 0x3E
+  let processor = run(&[0x3E, 0x3E, 0xD6, 0x3E], 2);
+
+  assert_eq!(a(&processor), 0x00);
+  assert_eq!(flags(&processor), ZERO_FLAG | SUBTRACT_FLAG);
+
+  // LD A,0x3E; SUB 0x0F
+  let processor = run(&[0x3E, 0x3E, 0xD6, 0x0F], 2);
+
+  assert_eq!(a(&processor), 0x2F);
+  assert_eq!(flags(&processor), SUBTRACT_FLAG | HALF_CARRY_FLAG);
+
+  // LD A,0x3E; SUB 0x40
+  let processor = run(&[0x3E, 0x3E, 0xD6, 0x40], 2);
+
+  assert_eq!(a(&processor), 0xFE);
+  assert_eq!(flags(&processor), SUBTRACT_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn subtract_with_carry_borrows_through_both_nibbles() {
+  // SCF; LD A,0x3B; SBC A,0x2A
+  let processor = run(&[0x37, 0x3E, 0x3B, 0xDE, 0x2A], 3);
+
+  assert_eq!(a(&processor), 0x10);
+  assert_eq!(flags(&processor), SUBTRACT_FLAG);
+
+  // SCF; LD A,0x3B; SBC A,0x4F
+  let processor = run(&[0x37, 0x3E, 0x3B, 0xDE, 0x4F], 3);
+
+  assert_eq!(a(&processor), 0xEB);
+  assert_eq!(flags(&processor), SUBTRACT_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
+
+  // SCF; LD A,0x00; SBC A,0xFF
+  let processor = run(&[0x37, 0x3E, 0x00, 0xDE, 0xFF], 3);
+
+  assert_eq!(a(&processor), 0x00);
+  assert_eq!(flags(&processor), ZERO_FLAG | SUBTRACT_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn compare_leaves_a_alone() {
+  // LD A,0x3C; CP 0x2F
+  let processor = run(&[0x3E, 0x3C, 0xFE, 0x2F], 2);
+
+  assert_eq!(a(&processor), 0x3C);
+  assert_eq!(flags(&processor), SUBTRACT_FLAG | HALF_CARRY_FLAG);
+
+  // LD A,0x3C; CP 0x3C
+  let processor = run(&[0x3E, 0x3C, 0xFE, 0x3C], 2);
+
+  assert_eq!(flags(&processor), ZERO_FLAG | SUBTRACT_FLAG);
+
+  // LD A,0x3C; CP 0x40
+  let processor = run(&[0x3E, 0x3C, 0xFE, 0x40], 2);
+
+  assert_eq!(flags(&processor), SUBTRACT_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn decimal_adjust_after_add() {
+  // LD A,0x45; ADD A,0x38; DAA
+  let processor = run(&[0x3E, 0x45, 0xC6, 0x38, 0x27], 3);
+
+  assert_eq!(a(&processor), 0x83);
+  assert_eq!(flags(&processor), 0);
+
+  // LD A,0x99; ADD A,0x01; DAA
+  let processor = run(&[0x3E, 0x99, 0xC6, 0x01, 0x27], 3);
+
+  assert_eq!(a(&processor), 0x00);
+  assert_eq!(flags(&processor), ZERO_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn decimal_adjust_after_subtract() {
+  // LD A,0x83; SUB 0x38; DAA
+  let processor = run(&[0x3E, 0x83, 0xD6, 0x38, 0x27], 3);
+
+  assert_eq!(a(&processor), 0x45);
+  assert_eq!(flags(&processor), SUBTRACT_FLAG);
+
+  // LD A,0x10; SUB 0x01; DAA
+  let processor = run(&[0x3E, 0x10, 0xD6, 0x01, 0x27], 3);
+
+  assert_eq!(a(&processor), 0x09);
+  assert_eq!(flags(&processor), SUBTRACT_FLAG);
+
+  // LD A,0x00; SUB 0x01; DAA
+  let processor = run(&[0x3E, 0x00, 0xD6, 0x01, 0x27], 3);
+
+  assert_eq!(a(&processor), 0x99);
+  assert_eq!(flags(&processor), SUBTRACT_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn add_to_stack_pointer_flags_come_from_the_low_byte() {
+  // LD SP,0xFFF8; ADD SP,0x08
+  let processor = run(&[0x31, 0xF8, 0xFF, 0xE8, 0x08], 2);
+
+  assert_eq!(processor.registers.get_stack_pointer(), 0x0000);
+  assert_eq!(flags(&processor), HALF_CARRY_FLAG | CARRY_FLAG);
+
+  // LD SP,0x0000; ADD SP,-1
+  let processor = run(&[0x31, 0x00, 0x00, 0xE8, 0xFF], 2);
+
+  assert_eq!(processor.registers.get_stack_pointer(), 0xFFFF);
+  assert_eq!(flags(&processor), 0);
+
+  // LD SP,0x00FF; ADD SP,-1 carries out of both nibbles despite subtracting
+  let processor = run(&[0x31, 0xFF, 0x00, 0xE8, 0xFF], 2);
+
+  assert_eq!(processor.registers.get_stack_pointer(), 0x00FE);
+  assert_eq!(flags(&processor), HALF_CARRY_FLAG | CARRY_FLAG);
+}
+
+#[test]
+fn load_stack_pointer_plus_offset_into_hl() {
+  // SCF; LD SP,0xFFF8; LD HL,SP+2
+  let processor = run(&[0x37, 0x31, 0xF8, 0xFF, 0xF8, 0x02], 3);
+
+  assert_eq!(processor.registers.read_word(REG_HL), 0xFFFA);
+  assert_eq!(processor.registers.get_stack_pointer(), 0xFFF8);
+  assert_eq!(flags(&processor), 0);
+
+  // LD SP,0x000F; LD HL,SP+1
+  let processor = run(&[0x31, 0x0F, 0x00, 0xF8, 0x01], 2);
+
+  assert_eq!(processor.registers.read_word(REG_HL), 0x0010);
+  assert_eq!(flags(&processor), HALF_CARRY_FLAG);
+}
+
+#[test]
+fn pop_af_clears_the_low_nibble_of_f() {
+  // LD BC,0x12FF; PUSH BC; POP AF
+  let processor = run(&[0x01, 0xFF, 0x12, 0xC5, 0xF1], 3);
+
+  assert_eq!(processor.registers.read_word(REG_AF), 0x12F0);
+}
+
+#[test]
+fn push_stores_high_byte_first_and_pop_reverses_it() {
+  // LD BC,0x1234; PUSH BC
+  let processor = run(&[0x01, 0x34, 0x12, 0xC5], 2);
+
+  assert_eq!(processor.registers.get_stack_pointer(), STACK_START - 2);
+  assert_eq!(processor.memory.read_byte(STACK_START - 1), 0x12);
+  assert_eq!(processor.memory.read_byte(STACK_START - 2), 0x34);
+
+  // LD BC,0x1234; PUSH BC; POP DE
+  let processor = run(&[0x01, 0x34, 0x12, 0xC5, 0xD1], 3);
+
+  assert_eq!(processor.registers.read_word(REG_DE), 0x1234);
+  assert_eq!(processor.registers.get_stack_pointer(), STACK_START);
+}
+
+// Runs the setup instructions, then returns how long the last one took
+fn run_last(program: &[u8], steps: usize) -> (Processor<FlatMemory>, u32) {
+  let mut processor = run(program, steps - 1);
+  let cycles = processor.step();
+
+  (processor, cycles)
+}
+
+#[test]
+fn rotate_register() {
+  // LD B,0x85; RLC B
+  let (processor, cycles) = run_last(&[0x06, 0x85, 0xCB, 0x00], 2);
+
+  assert_eq!(processor.registers.read_byte(REG_B), 0x0B);
+  assert_eq!(flags(&processor), CARRY_FLAG);
+  assert_eq!(cycles, 8);
+
+  // LD A,0x01; SRL A
+  let (processor, cycles) = run_last(&[0x3E, 0x01, 0xCB, 0x3F], 2);
+
+  assert_eq!(a(&processor), 0x00);
+  assert_eq!(flags(&processor), ZERO_FLAG | CARRY_FLAG);
+  assert_eq!(cycles, 8);
+}
+
+#[test]
+fn shift_address_hl() {
+  // LD HL,0xC000; LD (HL),0x81; SCF; RL (HL)
+  let (processor, cycles) = run_last(&[0x21, 0x00, 0xC0, 0x36, 0x81, 0x37, 0xCB, 0x16], 4);
+
+  assert_eq!(processor.memory.read_byte(DATA_ADDRESS), 0x03);
+  assert_eq!(flags(&processor), CARRY_FLAG);
+  assert_eq!(cycles, 16);
+
+  // LD HL,0xC000; LD (HL),0x81; SRA (HL)
+  let (processor, cycles) = run_last(&[0x21, 0x00, 0xC0, 0x36, 0x81, 0xCB, 0x2E], 3);
+
+  assert_eq!(processor.memory.read_byte(DATA_ADDRESS), 0xC0);
+  assert_eq!(flags(&processor), CARRY_FLAG);
+  assert_eq!(cycles, 16);
+
+  // LD HL,0xC000; LD (HL),0xF0; SWAP (HL)
+  let (processor, cycles) = run_last(&[0x21, 0x00, 0xC0, 0x36, 0xF0, 0xCB, 0x36], 3);
+
+  assert_eq!(processor.memory.read_byte(DATA_ADDRESS), 0x0F);
+  assert_eq!(flags(&processor), 0);
+  assert_eq!(cycles, 16);
+}
+
+#[test]
+fn bit_register() {
+  // SCF; LD H,0x7F; BIT 7,H leaves carry alone
+  let (processor, cycles) = run_last(&[0x37, 0x26, 0x7F, 0xCB, 0x7C], 3);
+
+  assert_eq!(flags(&processor), ZERO_FLAG | HALF_CARRY_FLAG | CARRY_FLAG);
+  assert_eq!(processor.registers.read_byte(REG_H), 0x7F);
+  assert_eq!(cycles, 8);
+}
+
+#[test]
+fn bit_address_hl_only_reads() {
+  // LD HL,0xC000; LD (HL),0x01; BIT 0,(HL)
+  let (processor, cycles) = run_last(&[0x21, 0x00, 0xC0, 0x36, 0x01, 0xCB, 0x46], 3);
+
+  assert_eq!(flags(&processor), HALF_CARRY_FLAG);
+  assert_eq!(processor.memory.read_byte(DATA_ADDRESS), 0x01);
+  assert_eq!(cycles, 12);
+}
+
+#[test]
+fn reset_and_set_register() {
+  // LD A,0xFF; RES 0,A
+  let (processor, cycles) = run_last(&[0x3E, 0xFF, 0xCB, 0x87], 2);
+
+  assert_eq!(a(&processor), 0xFE);
+  assert_eq!(flags(&processor), 0);
+  assert_eq!(cycles, 8);
+
+  // LD A,0x00; SET 3,A
+  let (processor, cycles) = run_last(&[0x3E, 0x00, 0xCB, 0xDF], 2);
+
+  assert_eq!(a(&processor), 0x08);
+  assert_eq!(cycles, 8);
+}
+
+#[test]
+fn reset_and_set_address_hl() {
+  // LD HL,0xC000; LD (HL),0xFF; RES 7,(HL)
+  let (processor, cycles) = run_last(&[0x21, 0x00, 0xC0, 0x36, 0xFF, 0xCB, 0xBE], 3);
+
+  assert_eq!(processor.memory.read_byte(DATA_ADDRESS), 0x7F);
+  assert_eq!(cycles, 16);
+
+  // LD HL,0xC000; SET 0,(HL)
+  let (processor, cycles) = run_last(&[0x21, 0x00, 0xC0, 0xCB, 0xC6], 2);
+
+  assert_eq!(processor.memory.read_byte(DATA_ADDRESS), 0x01);
+  assert_eq!(cycles, 16);
+}
+
+#[test]
+fn relative_jump_cycles() {
+  // JR NZ,+5 with Z clear
+  let (processor, cycles) = run_last(&[0x20, 0x05], 1);
+
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 7);
+  assert_eq!(cycles, 12);
+
+  // JR Z,+5 with Z clear
+  let (processor, cycles) = run_last(&[0x28, 0x05], 1);
+
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 2);
+  assert_eq!(cycles, 8);
+}
+
+#[test]
+fn absolute_jump_cycles() {
+  // JP NZ,0x0200 with Z clear
+  let (processor, cycles) = run_last(&[0xC2, 0x00, 0x02], 1);
+
+  assert_eq!(processor.registers.get_program_counter(), 0x0200);
+  assert_eq!(cycles, 16);
+
+  // JP Z,0x0200 with Z clear
+  let (processor, cycles) = run_last(&[0xCA, 0x00, 0x02], 1);
+
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 3);
+  assert_eq!(cycles, 12);
+}
+
+#[test]
+fn call_cycles() {
+  // CALL NZ,0x0200 with Z clear
+  let (processor, cycles) = run_last(&[0xC4, 0x00, 0x02], 1);
+
+  assert_eq!(processor.registers.get_program_counter(), 0x0200);
+  assert_eq!(processor.memory.read_word(STACK_START - 2), PROGRAM_START + 3);
+  assert_eq!(cycles, 24);
+
+  // CALL Z,0x0200 with Z clear
+  let (processor, cycles) = run_last(&[0xCC, 0x00, 0x02], 1);
+
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 3);
+  assert_eq!(processor.registers.get_stack_pointer(), STACK_START);
+  assert_eq!(cycles, 12);
+}
+
+#[test]
+fn return_cycles() {
+  // CALL 0x0104; NOP; RET NZ with Z clear
+  let (processor, cycles) = run_last(&[0xCD, 0x04, 0x01, 0x00, 0xC0], 2);
+
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 3);
+  assert_eq!(cycles, 20);
+
+  // CALL 0x0104; NOP; RET Z with Z clear
+  let (processor, cycles) = run_last(&[0xCD, 0x04, 0x01, 0x00, 0xC8], 2);
+
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 5);
+  assert_eq!(cycles, 8);
+
+  // CALL 0x0104; NOP; RET
+  let (processor, cycles) = run_last(&[0xCD, 0x04, 0x01, 0x00, 0xC9], 2);
+
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 3);
+  assert_eq!(cycles, 16);
+}
+
+#[test]
+fn restart_cycles() {
+  // RST 0x38
+  let (processor, cycles) = run_last(&[0xFF], 1);
+
+  assert_eq!(processor.registers.get_program_counter(), 0x0038);
+  assert_eq!(processor.memory.read_word(STACK_START - 2), PROGRAM_START + 1);
+  assert_eq!(cycles, 16);
+}
+
+#[test]
+fn interrupt_dispatch() {
+  let mut processor = processor(&[0x00]);
+
+  processor.registers.set_interrupt_master_enable(true);
+  processor.memory.write_byte(INTERRUPT_ENABLE_REG, Interrupt::Timer.mask());
+  processor.memory.write_byte(INTERRUPT_FLAG_REG, Interrupt::Timer.mask());
+
+  assert_eq!(processor.step(), 20);
+  assert_eq!(processor.registers.get_program_counter(), Interrupt::Timer.vector());
+  assert_eq!(processor.memory.read_word(STACK_START - 2), PROGRAM_START);
+  assert_eq!(processor.memory.read_byte(INTERRUPT_FLAG_REG), 0x00);
+  assert!(!processor.registers.get_interrupt_master_enable());
+}
+
+#[test]
+fn enable_interrupts_waits_for_the_next_instruction() {
+  // EI; NOP; NOP
+  let mut processor = processor(&[0xFB, 0x00, 0x00]);
+
+  processor.memory.write_byte(INTERRUPT_ENABLE_REG, Interrupt::VBlank.mask());
+  processor.memory.write_byte(INTERRUPT_FLAG_REG, Interrupt::VBlank.mask());
+
+  processor.step();
+  assert!(!processor.registers.get_interrupt_master_enable());
+
+  // The NOP after EI still runs before the interrupt is taken
+  processor.step();
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 2);
+  assert!(processor.registers.get_interrupt_master_enable());
+
+  assert_eq!(processor.step(), 20);
+  assert_eq!(processor.registers.get_program_counter(), Interrupt::VBlank.vector());
+  assert_eq!(processor.memory.read_word(STACK_START - 2), PROGRAM_START + 2);
+}
+
+#[test]
+fn halt_with_interrupt_pending_and_ime_clear_repeats_the_next_byte() {
+  // HALT; INC A; NOP
+  let mut processor = processor(&[0x76, 0x3C, 0x00]);
+
+  processor.memory.write_byte(INTERRUPT_ENABLE_REG, Interrupt::Joypad.mask());
+  processor.memory.write_byte(INTERRUPT_FLAG_REG, Interrupt::Joypad.mask());
+
+  processor.step();
+  assert_eq!(processor.state, State::Running);
+
+  processor.step();
+  processor.step();
+  assert_eq!(a(&processor), 2);
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 2);
+}
+
+#[test]
+fn halt_waits_for_an_interrupt_without_servicing_it() {
+  // HALT; INC A
+  let mut processor = processor(&[0x76, 0x3C]);
+
+  processor.memory.write_byte(INTERRUPT_ENABLE_REG, Interrupt::Serial.mask());
+  processor.step();
+
+  for _ in 0..3 {
+    assert_eq!(processor.step(), 4);
+    assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 1);
+  }
+
+  processor.memory.write_byte(INTERRUPT_FLAG_REG, Interrupt::Serial.mask());
+  processor.step();
+  assert_eq!(a(&processor), 1);
+  assert_eq!(processor.memory.read_byte(INTERRUPT_FLAG_REG), Interrupt::Serial.mask());
+}
+
+#[test]
+fn enable_interrupts_then_halt_returns_to_the_halt() {
+  // EI; HALT
+  let mut processor = processor(&[0xFB, 0x76]);
+
+  processor.memory.write_byte(INTERRUPT_ENABLE_REG, Interrupt::Timer.mask());
+  processor.memory.write_byte(INTERRUPT_FLAG_REG, Interrupt::Timer.mask());
+
+  processor.step();
+  processor.step();
+
+  assert_eq!(processor.step(), 20);
+  assert_eq!(processor.registers.get_program_counter(), Interrupt::Timer.vector());
+  assert_eq!(processor.memory.read_word(STACK_START - 2), PROGRAM_START + 1);
+}
+
+#[test]
+fn stop_waits_for_a_button_press() {
+  // STOP; INC A
+  let mut processor = processor(&[0x10, 0x00, 0x3C]);
+
+  processor.memory.write_byte(DIVIDER_REG, 0x55);
+  processor.step();
+
+  assert_eq!(processor.state, State::Stopped);
+  assert_eq!(processor.memory.read_byte(DIVIDER_REG), 0x00);
+
+  assert_eq!(processor.step(), 4);
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 2);
+
+  // A on the button line
+  processor.memory.write_byte(JOYPAD_REG, 0xDE);
+  processor.step();
+  assert_eq!(a(&processor), 1);
+}
+
+#[test]
+fn stop_with_a_button_held_halts_instead() {
+  // STOP; INC A
+  let mut processor = processor(&[0x10, 0x00, 0x3C]);
+
+  processor.memory.write_byte(JOYPAD_REG, 0xDE);
+  processor.step();
+
+  assert_eq!(processor.state, State::Halted);
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 2);
+}
+
+#[test]
+fn stop_with_a_button_held_and_an_interrupt_pending_is_a_nop() {
+  // STOP; INC A
+  let mut processor = processor(&[0x10, 0x3C]);
+
+  processor.memory.write_byte(JOYPAD_REG, 0xDE);
+  processor.memory.write_byte(INTERRUPT_ENABLE_REG, Interrupt::Joypad.mask());
+  processor.memory.write_byte(INTERRUPT_FLAG_REG, Interrupt::Joypad.mask());
+  processor.step();
+
+  assert_eq!(processor.state, State::Running);
+  assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 1);
+
+  processor.step();
+  assert_eq!(a(&processor), 1);
+}
+
+#[test]
+fn illegal_opcodes_lock_up_the_cpu() {
+  for &opcode in &[0xD3, 0xDB, 0xDD, 0xE3, 0xE4, 0xEB, 0xEC, 0xED, 0xF4, 0xFC, 0xFD] {
+    // <illegal>; INC A
+    let mut processor = processor(&[opcode, 0x3C]);
+
+    assert_eq!(format!("{:?}", Instruction(opcode)), format!("{:#04x} Illegal", opcode));
+
+    processor.step();
+    assert_eq!(processor.state, State::Locked);
+
+    // Not even an interrupt gets it going again, though time still passes
+    processor.registers.set_interrupt_master_enable(true);
+    processor.memory.write_byte(INTERRUPT_ENABLE_REG, Interrupt::Joypad.mask());
+    processor.memory.write_byte(INTERRUPT_FLAG_REG, Interrupt::Joypad.mask());
+
+    let cycles = processor.cycles();
+    let a_before = a(&processor);
+
+    assert_eq!(processor.step(), 4);
+    assert_eq!(processor.cycles(), cycles + 4);
+    assert_eq!(processor.registers.get_program_counter(), PROGRAM_START + 1);
+    assert_eq!(a(&processor), a_before);
+  }
+}
+
+#[test]
+fn bootroms_hand_over_with_each_models_registers() {
+  // Model, header checksum, AF, BC, DE, HL
+  let expected = [
+    (Model::Dmg0, 0x12, 0x0100, 0xFF13, 0x00C1, 0x8403),
+    (Model::Dmg, 0x12, 0x01B0, 0x0013, 0x00D8, 0x014D),
+    (Model::Dmg, 0x00, 0x0180, 0x0013, 0x00D8, 0x014D),
+    (Model::Mgb, 0x12, 0xFFB0, 0x0013, 0x00D8, 0x014D),
+    (Model::Mgb, 0x00, 0xFF80, 0x0013, 0x00D8, 0x014D),
+    (Model::Sgb, 0x12, 0x0100, 0x0014, 0x0000, 0xC060),
+    (Model::Sgb2, 0x12, 0xFF00, 0x0014, 0x0000, 0xC060),
+    (Model::Cgb, 0x12, 0x1180, 0x0000, 0xFF56, 0x000D),
+    (Model::Agb, 0x12, 0x1100, 0x0100, 0xFF56, 0x000D),
+  ];
+
+  for &(model, header_checksum, af, bc, de, hl) in &expected {
+    let registers = Registers::post_boot(model.post_boot_registers(header_checksum));
+
+    assert_eq!(
+      [registers.read_word(REG_AF), registers.read_word(REG_BC), registers.read_word(REG_DE), registers.read_word(REG_HL)],
+      [af, bc, de, hl],
+      "{:?} with header checksum {:#04x}", model, header_checksum
+    );
+    assert_eq!(registers.get_stack_pointer(), 0xFFFE);
+    assert_eq!(registers.get_program_counter(), 0x0100);
+  }
+}
@@ -0,0 +1,179 @@
+use std::io::{self, Write};
+
+use super::interrupts::{Interrupt, Interrupts};
+
+const REG_DATA: u16 = 0xFF01; // SB
+const REG_CONTROL: u16 = 0xFF02; // SC
+
+const TRANSFER_FLAG: u8 = 0x80;
+const FAST_CLOCK_FLAG: u8 = 0x02; // CGB only
+const INTERNAL_CLOCK_FLAG: u8 = 0x01;
+
+// The internal clock runs at 8192 Hz, or 262144 Hz with the CGB's fast clock
+const CYCLES_PER_BIT: u32 = 512;
+const FAST_CYCLES_PER_BIT: u32 = 16;
+
+// Whatever is plugged into the link port
+pub trait SerialDevice {
+  // Exchanges a byte clocked out by the Game Boy for the one shifted in at the same time.
+  // Returning None holds the transfer back until the device is ready, and it is asked again
+  fn transfer(&mut self, value: u8) -> Option<u8>;
+
+  // Called as time passes, with the byte waiting to be sent while the Game Boy is set to the
+  // external clock. Devices that drive the clock themselves return the byte they shift in
+  fn tick(&mut self, _cycles: u32, _waiting: Option<u8>) -> Option<u8> {
+    None
+  }
+}
+
+// Prints everything the game sends, which is how test ROMs report results.
+// Nothing drives the input line, so it reads back as all ones
+pub struct StdoutDevice;
+
+impl SerialDevice for StdoutDevice {
+  fn transfer(&mut self, value: u8) -> Option<u8> {
+    print!("{}", value as char);
+    io::stdout().flush().ok();
+
+    Some(0xFF)
+  }
+}
+
+pub struct Serial {
+  data: u8,
+  control: u8,
+  control_mask: u8, // The bits of SC the model implements
+  cycles: u32, // Until the transfer in progress completes
+  device: Box<dyn SerialDevice>,
+  output: Vec<u8>, // Every byte sent so far
+}
+
+impl Serial {
+  pub fn new(color: bool) -> Serial {
+    let mut control_mask = TRANSFER_FLAG | INTERNAL_CLOCK_FLAG;
+
+    if color {
+      control_mask |= FAST_CLOCK_FLAG;
+    }
+
+    Serial {
+      data: 0,
+      control: 0,
+      control_mask,
+      cycles: 0,
+      device: Box::new(StdoutDevice),
+      output: Vec::new(),
+    }
+  }
+
+  pub fn connect(&mut self, device: Box<dyn SerialDevice>) {
+    self.device = device;
+  }
+
+  pub fn output(&self) -> &[u8] {
+    &self.output
+  }
+
+  pub fn tick(&mut self, cycles: u32, interrupts: &mut Interrupts) {
+    let external = self.control & (TRANSFER_FLAG | INTERNAL_CLOCK_FLAG) == TRANSFER_FLAG;
+    let received = self.device.tick(cycles, if external { Some(self.data) } else { None });
+
+    if let (true, Some(value)) = (external, received) {
+      self.complete(value, interrupts);
+    }
+
+    if self.control & (TRANSFER_FLAG | INTERNAL_CLOCK_FLAG) != TRANSFER_FLAG | INTERNAL_CLOCK_FLAG {
+      return;
+    }
+
+    self.cycles = self.cycles.saturating_sub(cycles);
+
+    // Once all eight bits are out, the device may still hold the transfer back
+    if self.cycles == 0 {
+      if let Some(value) = self.device.transfer(self.data) {
+        self.complete(value, interrupts);
+      }
+    }
+  }
+
+  fn complete(&mut self, value: u8, interrupts: &mut Interrupts) {
+    self.output.push(self.data);
+    self.data = value;
+    self.control &= !TRANSFER_FLAG;
+
+    interrupts.request(Interrupt::Serial);
+  }
+
+  pub fn read_register(&self, register: u16) -> u8 {
+    match register {
+      REG_DATA => self.data,
+      REG_CONTROL => self.control | !self.control_mask,
+      _ => unreachable!("Invalid serial register: {:#06x}", register),
+    }
+  }
+
+  pub fn write_register(&mut self, register: u16, value: u8) {
+    match register {
+      REG_DATA => self.data = value,
+      REG_CONTROL => {
+        self.control = value & self.control_mask;
+
+        if self.control & TRANSFER_FLAG != 0 {
+          let cycles_per_bit = if self.control & FAST_CLOCK_FLAG != 0 { FAST_CYCLES_PER_BIT } else { CYCLES_PER_BIT };
+
+          self.cycles = cycles_per_bit * 8;
+        }
+      }
+      _ => unreachable!("Invalid serial register: {:#06x}", register),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Answers every byte with its complement
+  struct Inverter;
+
+  impl SerialDevice for Inverter {
+    fn transfer(&mut self, value: u8) -> Option<u8> {
+      Some(!value)
+    }
+  }
+
+  #[test]
+  fn internal_clock_transfers_take_eight_bits() {
+    let mut interrupts = Interrupts::default();
+    let mut serial = Serial::new(false);
+
+    serial.connect(Box::new(Inverter));
+    serial.write_register(REG_DATA, 0x41);
+    serial.write_register(REG_CONTROL, TRANSFER_FLAG | INTERNAL_CLOCK_FLAG);
+
+    serial.tick(8 * CYCLES_PER_BIT - 4, &mut interrupts);
+    assert!(serial.output().is_empty());
+    assert_eq!(serial.read_register(REG_CONTROL), 0xFF);
+
+    serial.tick(4, &mut interrupts);
+    assert_eq!(serial.output(), b"A");
+    assert_eq!(serial.read_register(REG_DATA), 0xBE);
+    assert_eq!(serial.read_register(REG_CONTROL) & TRANSFER_FLAG, 0);
+    assert_ne!(interrupts.read_flag() & Interrupt::Serial.mask(), 0);
+  }
+
+  #[test]
+  fn external_clock_waits_for_the_device() {
+    let mut interrupts = Interrupts::default();
+    let mut serial = Serial::new(false);
+
+    serial.connect(Box::new(Inverter));
+    serial.write_register(REG_DATA, 0x41);
+    serial.write_register(REG_CONTROL, TRANSFER_FLAG);
+    serial.tick(8 * CYCLES_PER_BIT, &mut interrupts);
+
+    assert!(serial.output().is_empty());
+    assert_ne!(serial.read_register(REG_CONTROL) & TRANSFER_FLAG, 0);
+    assert_eq!(interrupts.read_flag() & Interrupt::Serial.mask(), 0);
+  }
+}
@@ -0,0 +1,61 @@
+const REGISTERS_START: u16 = 0xFF10;
+const REG_POWER: u16 = 0xFF26; // NR52
+const WAVE_START: u16 = 0xFF30;
+
+const POWER_FLAG: u8 = 0x80;
+
+// Bits that always read back as 1 for each register from NR10 to the end of
+// the unused area before wave RAM, which covers every write-only bit
+const READ_MASKS: [u8; 0x20] = [
+  0x80, 0x3F, 0x00, 0xFF, 0xBF, // NR10-NR14
+  0xFF, 0x3F, 0x00, 0xFF, 0xBF, // Unused, NR21-NR24
+  0x7F, 0xFF, 0x9F, 0xFF, 0xBF, // NR30-NR34
+  0xFF, 0xFF, 0x00, 0x00, 0xBF, // Unused, NR41-NR44
+  0x00, 0x00, 0x70, // NR50-NR52
+  0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, // Unused
+];
+
+// The APU's registers. No sound is generated yet, so every channel reads as off
+pub struct Sound {
+  registers: [u8; 0x20],
+  wave: [u8; 0x10],
+  powered: bool,
+}
+
+impl Sound {
+  pub fn new() -> Sound {
+    Sound {
+      registers: [0; 0x20],
+      wave: [0; 0x10],
+      powered: false,
+    }
+  }
+
+  pub fn read_register(&self, register: u16) -> u8 {
+    match register {
+      REG_POWER => if self.powered { POWER_FLAG | READ_MASKS[0x16] } else { READ_MASKS[0x16] },
+      WAVE_START ..= 0xFF3F => self.wave[(register - WAVE_START) as usize],
+      _ => {
+        let index = (register - REGISTERS_START) as usize;
+
+        self.registers[index] | READ_MASKS[index]
+      }
+    }
+  }
+
+  pub fn write_register(&mut self, register: u16, value: u8) {
+    match register {
+      REG_POWER => {
+        self.powered = (value & POWER_FLAG) != 0;
+
+        // Powering off clears every register, and they ignore writes until powered back on
+        if !self.powered {
+          self.registers = [0; 0x20];
+        }
+      }
+      WAVE_START ..= 0xFF3F => self.wave[(register - WAVE_START) as usize] = value,
+      _ if self.powered => self.registers[(register - REGISTERS_START) as usize] = value,
+      _ => {}
+    }
+  }
+}
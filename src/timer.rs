@@ -0,0 +1,229 @@
+use super::interrupts::{Interrupt, Interrupts};
+use super::processor::CYCLES_PER_MACHINE_CYCLE;
+
+const REG_DIVIDER: u16 = 0xFF04; // DIV
+const REG_COUNTER: u16 = 0xFF05; // TIMA
+const REG_MODULO: u16 = 0xFF06; // TMA
+const REG_CONTROL: u16 = 0xFF07; // TAC
+
+// TAC only implements the enable bit and the clock select
+const CONTROL_UNUSED: u8 = 0xF8;
+const CONTROL_ENABLE: u8 = 0x04;
+const CONTROL_CLOCK_SELECT: u8 = 0x03;
+
+// The divider bit each clock select feeds into TIMA: 4096, 262144, 65536 and 16384 Hz
+const CLOCK_BITS: [u16; 4] = [9, 3, 5, 7];
+
+#[derive(Default)]
+pub struct Timer {
+  divider: u16, // DIV is the upper byte of this free running counter
+  counter: u8,
+  modulo: u8,
+  control: u8,
+  overflowed: bool, // TIMA overflowed this M-cycle and reads 0 until the reload in the next one
+  reloading: bool, // TIMA was reloaded from TMA this M-cycle
+}
+
+impl Timer {
+  // Sets the internal counter, for when the bootrom is skipped
+  pub fn set_divider(&mut self, divider: u16) {
+    self.divider = divider;
+  }
+
+  pub fn tick(&mut self, cycles: u32, interrupts: &mut Interrupts) {
+    for _ in 0..cycles / CYCLES_PER_MACHINE_CYCLE {
+      self.reloading = false;
+
+      if self.overflowed {
+        self.overflowed = false;
+        self.reloading = true;
+        self.counter = self.modulo;
+
+        interrupts.request(Interrupt::Timer);
+      }
+
+      let signal = self.signal();
+
+      self.divider = self.divider.wrapping_add(CYCLES_PER_MACHINE_CYCLE as u16);
+      self.detect_falling_edge(signal);
+    }
+  }
+
+  pub fn read_register(&self, register: u16) -> u8 {
+    match register {
+      REG_DIVIDER => (self.divider >> 8) as u8,
+      REG_COUNTER => self.counter,
+      REG_MODULO => self.modulo,
+      REG_CONTROL => self.control | CONTROL_UNUSED,
+      _ => unreachable!("Invalid timer register: {:#06x}", register),
+    }
+  }
+
+  // Resetting the divider or changing TAC can drop the selected bit, which
+  // increments TIMA just as if the divider had counted past it
+  pub fn write_register(&mut self, register: u16, value: u8) {
+    let signal = self.signal();
+
+    match register {
+      // Any write clears the whole counter, not just the visible byte
+      REG_DIVIDER => self.divider = 0,
+
+      // The reload wins over a write in the same cycle, while a write
+      // in the cycle before it cancels both the reload and the interrupt
+      REG_COUNTER => if !self.reloading {
+        self.counter = value;
+        self.overflowed = false;
+      },
+
+      // TMA written while it's being reloaded goes straight through to TIMA
+      REG_MODULO => {
+        self.modulo = value;
+
+        if self.reloading {
+          self.counter = value;
+        }
+      }
+
+      REG_CONTROL => self.control = value & !CONTROL_UNUSED,
+      _ => unreachable!("Invalid timer register: {:#06x}", register),
+    }
+
+    self.detect_falling_edge(signal);
+  }
+
+  // The selected divider bit ANDed with the enable bit, which TIMA counts falling edges of
+  fn signal(&self) -> bool {
+    let bit = CLOCK_BITS[(self.control & CONTROL_CLOCK_SELECT) as usize];
+
+    self.control & CONTROL_ENABLE != 0 && (self.divider >> bit) & 0x01 != 0
+  }
+
+  fn detect_falling_edge(&mut self, previous: bool) {
+    if !previous || self.signal() {
+      return;
+    }
+
+    let (counter, overflow) = self.counter.overflowing_add(1);
+
+    self.counter = counter;
+
+    if overflow {
+      self.overflowed = true;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  // Enabled at 262144 Hz, so TIMA counts when bit 3 of the divider falls, every 16 cycles
+  const FAST_CLOCK: u8 = CONTROL_ENABLE | 0x01;
+
+  fn timer_flag(interrupts: &Interrupts) -> bool {
+    interrupts.read_flag() & Interrupt::Timer.mask() != 0
+  }
+
+  // TIMA about to overflow on the next falling edge
+  fn overflowing_timer(interrupts: &mut Interrupts) -> Timer {
+    let mut timer = Timer::default();
+
+    timer.write_register(REG_CONTROL, FAST_CLOCK);
+    timer.write_register(REG_COUNTER, 0xFF);
+    timer.write_register(REG_MODULO, 0x23);
+    timer.tick(16, interrupts);
+
+    timer
+  }
+
+  #[test]
+  fn counts_falling_edges_of_the_selected_bit() {
+    let mut interrupts = Interrupts::default();
+    let mut timer = Timer::default();
+
+    timer.write_register(REG_CONTROL, FAST_CLOCK);
+    timer.tick(64, &mut interrupts);
+
+    assert_eq!(timer.read_register(REG_COUNTER), 4);
+  }
+
+  #[test]
+  fn resetting_the_divider_can_count() {
+    let mut interrupts = Interrupts::default();
+    let mut timer = Timer::default();
+
+    timer.write_register(REG_CONTROL, FAST_CLOCK);
+    timer.tick(4, &mut interrupts);
+    timer.write_register(REG_DIVIDER, 0x00);
+    assert_eq!(timer.read_register(REG_COUNTER), 0);
+
+    // Bit 3 is set from the eighth cycle
+    timer.tick(8, &mut interrupts);
+    timer.write_register(REG_DIVIDER, 0x00);
+    assert_eq!(timer.read_register(REG_COUNTER), 1);
+    assert_eq!(timer.read_register(REG_DIVIDER), 0);
+  }
+
+  #[test]
+  fn changing_tac_can_count() {
+    let mut interrupts = Interrupts::default();
+    let mut timer = Timer::default();
+
+    timer.write_register(REG_CONTROL, FAST_CLOCK);
+    timer.tick(8, &mut interrupts);
+
+    // Bit 9 is still clear
+    timer.write_register(REG_CONTROL, CONTROL_ENABLE);
+    assert_eq!(timer.read_register(REG_COUNTER), 1);
+
+    // So does disabling the timer while the bit is set
+    timer.write_register(REG_CONTROL, FAST_CLOCK);
+    timer.write_register(REG_CONTROL, 0x01);
+    assert_eq!(timer.read_register(REG_COUNTER), 2);
+  }
+
+  #[test]
+  fn reads_zero_for_a_cycle_after_overflowing() {
+    let mut interrupts = Interrupts::default();
+    let mut timer = overflowing_timer(&mut interrupts);
+
+    assert_eq!(timer.read_register(REG_COUNTER), 0x00);
+    assert!(!timer_flag(&interrupts));
+
+    timer.tick(4, &mut interrupts);
+    assert_eq!(timer.read_register(REG_COUNTER), 0x23);
+    assert!(timer_flag(&interrupts));
+  }
+
+  #[test]
+  fn writing_tima_before_the_reload_cancels_it() {
+    let mut interrupts = Interrupts::default();
+    let mut timer = overflowing_timer(&mut interrupts);
+
+    timer.write_register(REG_COUNTER, 0x50);
+    timer.tick(4, &mut interrupts);
+
+    assert_eq!(timer.read_register(REG_COUNTER), 0x50);
+    assert!(!timer_flag(&interrupts));
+  }
+
+  #[test]
+  fn writes_during_the_reload() {
+    let mut interrupts = Interrupts::default();
+    let mut timer = overflowing_timer(&mut interrupts);
+
+    timer.tick(4, &mut interrupts);
+
+    // TIMA writes are lost, while TMA writes go through to TIMA
+    timer.write_register(REG_COUNTER, 0x10);
+    assert_eq!(timer.read_register(REG_COUNTER), 0x23);
+
+    timer.write_register(REG_MODULO, 0x77);
+    assert_eq!(timer.read_register(REG_COUNTER), 0x77);
+
+    // After that it's back to normal
+    timer.tick(4, &mut interrupts);
+    timer.write_register(REG_MODULO, 0x88);
+    assert_eq!(timer.read_register(REG_COUNTER), 0x77);
+  }
+}